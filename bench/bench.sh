@@ -0,0 +1,76 @@
+#!/bin/sh
+#
+# Compares search throughput of the working tree against an older revision.
+#
+# Usage: bench/bench.sh [BASE_REV]
+#
+#   BASE_REV      revision to compare against (default: HEAD~1)
+#   BENCH_SIZE    size of the generated input, in MiB (default: 2048)
+#   BENCH_DIR     where to put the corpus and builds (default: /tmp/grep-bench)
+#
+# Results are printed and also written to bench_output.txt.
+
+set -e
+
+cd "$(dirname "$0")/.."
+
+base_rev="${1:-HEAD~1}"
+size_mib="${BENCH_SIZE:-2048}"
+bench_dir="${BENCH_DIR:-/tmp/grep-bench}"
+corpus="$bench_dir/corpus-${size_mib}M.txt"
+output="bench_output.txt"
+
+mkdir -p "$bench_dir"
+
+# Generate a 64 MiB seed of varied log-like lines, then repeat it up to the
+# requested size. Only a few lines contain the rare needle.
+if [ ! -f "$corpus" ]; then
+  echo "Generating ${size_mib} MiB corpus at $corpus"
+  awk 'BEGIN {
+    srand(42)
+    split("alpha beta gamma delta epsilon zeta theta lambda sigma omega", words, " ")
+    while (bytes < 64 * 1024 * 1024) {
+      line = sprintf("%05d [%s] %s", NR++, (rand() < 0.1 ? "WARN" : "INFO"), words[int(rand() * 10) + 1])
+      for (i = 0; i < int(rand() * 12); i++) line = line " " words[int(rand() * 10) + 1]
+      if (rand() < 0.0001) line = line " needle-" int(rand() * 1000)
+      print line
+      bytes += length(line) + 1
+    }
+  }' > "$bench_dir/seed.txt"
+
+  : > "$corpus"
+  while [ "$(wc -c < "$corpus")" -lt $((size_mib * 1024 * 1024)) ]; do
+    cat "$bench_dir/seed.txt" >> "$corpus"
+  done
+fi
+
+# Build both versions in release mode
+rm -rf "$bench_dir/base"
+git worktree add --force --detach "$bench_dir/base" "$base_rev" > /dev/null
+trap 'git worktree remove --force "$bench_dir/base"' EXIT
+
+cargo build --release --quiet --manifest-path "$bench_dir/base/Cargo.toml" \
+  --target-dir "$bench_dir/target-base"
+cargo build --release --quiet --target-dir "$bench_dir/target-head"
+
+base_bin="$bench_dir/target-base/release/codecrafters-grep"
+head_bin="$bench_dir/target-head/release/codecrafters-grep"
+
+# Prints elapsed seconds and throughput of one run
+measure() {
+  start=$(date +%s.%N)
+  "$1" -E "$2" < "$corpus" > /dev/null || true
+  end=$(date +%s.%N)
+  echo "$start $end $size_mib" | awk '{ t = $2 - $1; printf "%8.2fs %9.1f MiB/s", t, $3 / t }'
+}
+
+{
+  echo "corpus: $corpus (${size_mib} MiB)"
+  echo "base:   $(git rev-parse --short "$base_rev")"
+  echo "head:   working tree"
+  echo
+  printf "%-24s %-28s %-28s\n" "pattern" "base" "head"
+  for pattern in 'needle-42' 'needle-\d+$' '^\d+ \[WARN\] omega' 'sigma (alpha|beta)' 'zzz'; do
+    printf "%-24s %-28s %-28s\n" "$pattern" "$(measure "$base_bin" "$pattern")" "$(measure "$head_bin" "$pattern")"
+  done
+} | tee "$output"
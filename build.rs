@@ -0,0 +1,141 @@
+//! Generates the Unicode property tables from the UCD files in `ucd/`
+//!
+//! The output is a Rust file in `OUT_DIR` that `src/unicode.rs` includes. Each
+//! property becomes a sorted list of non-overlapping code point ranges.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Code point ranges of every value of a property, by value name
+type Tables = BTreeMap<String, Vec<(u32, u32)>>;
+
+fn main() {
+    println!("cargo:rerun-if-changed=ucd");
+
+    let categories = with_category_groups(read_ranges("DerivedGeneralCategory.txt"));
+    let scripts = read_ranges("Scripts.txt");
+    let mut binary = read_ranges("PropList.txt");
+    binary.extend(read_ranges("DerivedCoreProperties.txt"));
+
+    let mut out = String::from("// Generated by build.rs from the files in ucd/. Do not edit.\n\n");
+    write_tables(&mut out, "GENERAL_CATEGORIES", &categories);
+    write_tables(&mut out, "SCRIPTS", &scripts);
+    write_tables(&mut out, "BINARY_PROPERTIES", &binary);
+    write_aliases(&mut out);
+
+    let path = Path::new(&std::env::var("OUT_DIR").unwrap()).join("unicode_tables.rs");
+    fs::write(path, out).unwrap();
+}
+
+/// Reads a UCD file of `RANGE ; VALUE` lines into tables
+fn read_ranges(file: &str) -> Tables {
+    let mut tables = Tables::new();
+
+    for line in read_lines(file) {
+        // Skip properties with more than one value field, like `InCB`
+        let [range, value] = line[..] else {
+            continue;
+        };
+
+        let (start, end) = match range.split_once("..") {
+            Some((start, end)) => (parse_hex(start), parse_hex(end)),
+            None => (parse_hex(range), parse_hex(range)),
+        };
+        tables
+            .entry(value.to_string())
+            .or_default()
+            .push((start, end));
+    }
+
+    tables.values_mut().for_each(normalize);
+    tables
+}
+
+/// Returns the `;`-separated fields of every data line of a UCD file
+fn read_lines(file: &str) -> Vec<Vec<&'static str>> {
+    let text = fs::read_to_string(Path::new("ucd").join(file))
+        .unwrap()
+        .leak();
+
+    text.lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.split(';').map(str::trim).collect())
+        .collect()
+}
+
+fn parse_hex(text: &str) -> u32 {
+    u32::from_str_radix(text, 16).unwrap()
+}
+
+/// Adds the one-letter category groups like `L`, and `LC` for cased letters
+fn with_category_groups(mut categories: Tables) -> Tables {
+    let mut groups = Tables::new();
+    for (name, ranges) in &categories {
+        let group = name[..1].to_string();
+        groups.entry(group).or_default().extend(ranges);
+
+        if matches!(name.as_str(), "Lu" | "Ll" | "Lt") {
+            groups.entry("LC".to_string()).or_default().extend(ranges);
+        }
+    }
+
+    groups.values_mut().for_each(normalize);
+    categories.extend(groups);
+    categories
+}
+
+/// Sorts ranges, merges adjacent ones and drops surrogates, which aren't chars
+fn normalize(ranges: &mut Vec<(u32, u32)>) {
+    let mut split = vec![];
+    for &(start, end) in ranges.iter() {
+        if start < 0xD800 {
+            split.push((start, end.min(0xD7FF)));
+        }
+        if end > 0xDFFF {
+            split.push((start.max(0xE000), end));
+        }
+    }
+    split.sort_unstable();
+
+    ranges.clear();
+    for (start, end) in split {
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => ranges.push((start, end)),
+        }
+    }
+}
+
+fn write_tables(out: &mut String, name: &str, tables: &Tables) {
+    writeln!(out, "pub static {}: &[(&str, &[(char, char)])] = &[", name).unwrap();
+    for (value, ranges) in tables {
+        write!(out, "    ({:?}, &[", value).unwrap();
+        for (start, end) in ranges {
+            write!(out, "('\\u{{{:X}}}', '\\u{{{:X}}}'), ", start, end).unwrap();
+        }
+        writeln!(out, "]),").unwrap();
+    }
+    writeln!(out, "];\n").unwrap();
+}
+
+/// Writes the aliases of general categories and scripts, mapped to the names
+/// used in the tables
+fn write_aliases(out: &mut String) {
+    writeln!(out, "pub static ALIASES: &[(&str, &str)] = &[").unwrap();
+    for fields in read_lines("PropertyValueAliases.txt") {
+        // General categories are keyed by their short name, scripts by their long one
+        let canonical = match fields[..] {
+            ["gc", short, ..] => short,
+            ["sc", _, long, ..] => long,
+            _ => continue,
+        };
+
+        for alias in &fields[1..] {
+            writeln!(out, "    ({:?}, {:?}),", alias, canonical).unwrap();
+        }
+    }
+    writeln!(out, "];").unwrap();
+}
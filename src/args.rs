@@ -0,0 +1,332 @@
+use anyhow::{bail, Context, Result};
+
+use codecrafters_grep::Syntax;
+
+use crate::encoding::Encoding;
+use crate::searcher::{BinaryFiles, Output, Span};
+use crate::trace;
+
+/// Short options that take a value, like `-j 4` or `-j4`
+const SHORT_WITH_VALUE: &[char] = &['j', 'm'];
+
+/// Long options that take a value, like `--sort path` or `--sort=path`
+const LONG_WITH_VALUE: &[&str] = &[
+    "--binary-files",
+    "--encoding",
+    "--exclude",
+    "--exclude-dir",
+    "--include",
+    "--max-count",
+    "--pre",
+    "--pre-glob",
+    "--replace",
+    "--sort",
+];
+
+/// What `--explain` prints
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Explain {
+    /// The pattern tree
+    Tree,
+    /// The pattern tree and the compiled program
+    Program,
+}
+
+/// Parsed command-line options
+pub struct Args {
+    pub pattern: String,
+    /// Pattern dialect: basic (`-G`, the default), extended (`-E`) or Perl (`-P`)
+    pub syntax: Syntax,
+    /// Make `\d`, `\w`, `\s`, `\b` and case folding ASCII-only (`--ascii`)
+    pub ascii: bool,
+    /// Treat `\r\n` as the line ending for `$` (`--crlf`)
+    pub crlf: bool,
+    /// Transcode inputs without a byte order mark from this (`--encoding`)
+    pub encoding: Option<Encoding>,
+    /// Print the parsed pattern instead of searching, and the compiled
+    /// program too with `--explain=program`
+    pub explain: Option<Explain>,
+    /// Print each step the matcher takes on every line instead of searching,
+    /// stopping after this many steps (`--trace`)
+    pub trace: Option<u64>,
+    /// Let matches span lines, searching each input as a whole (`-U`)
+    pub multiline: bool,
+    /// Input and output records end with NUL instead of a newline (`-z`)
+    pub null_data: bool,
+    /// Follow file names with NUL instead of `:` (`-Z`)
+    pub null: bool,
+    pub files: Vec<String>,
+    pub binary_files: BinaryFiles,
+    /// Print matching lines, only file names with `-l` and `-L`, or nothing with `-q`
+    pub output: Output,
+    /// Only count matches of whole words (`-w`) or whole lines (`-x`)
+    pub span: Span,
+    /// Stop after this many matching lines per file (`-m`)
+    pub max_count: Option<u64>,
+    /// Print matching lines with every match replaced by this (`--replace`)
+    pub replace: Option<String>,
+    /// Write replacements back to the files (`--in-place`)
+    pub in_place: bool,
+    /// Keep a copy of each edited file with this added to its name (`--in-place=SUFFIX`)
+    pub backup_suffix: Option<String>,
+    /// Print replacements as a unified diff instead of writing them (`--dry-run`)
+    pub dry_run: bool,
+    /// Don't report missing or unreadable files (`-s`)
+    pub no_messages: bool,
+    pub mmap: bool,
+    /// Search the decompressed contents of compressed files (`--search-zip`)
+    pub search_zip: bool,
+    /// Search the output of this command run on each file (`--pre`)
+    pub pre: Option<String>,
+    /// Globs of files to run `--pre` on, all files if there are none (`--pre-glob`)
+    pub pre_glob: Vec<String>,
+    /// Search directories recursively (`-r`)
+    pub recursive: bool,
+    /// Number of files searched in parallel (`-j`)
+    pub jobs: usize,
+    /// Globs of files to search (`--include`)
+    pub include: Vec<String>,
+    /// Globs of files to skip (`--exclude`)
+    pub exclude: Vec<String>,
+    /// Globs of directories to skip (`--exclude-dir`)
+    pub exclude_dir: Vec<String>,
+    /// Respect `.gitignore`, `.ignore` and `.git/info/exclude` (`--ignore-files`)
+    pub ignore_files: bool,
+    /// Print results in file order (`--sort=path`) or as they finish (`--sort=none`)
+    pub sorted: bool,
+}
+
+/// A command-line argument split into an option and its value
+enum Token {
+    Option(String, Option<String>),
+    Positional(String),
+}
+
+/// Splits arguments into options and positional arguments, getopt style
+///
+/// Short options can be grouped (`-ar`), and a value can be attached (`-j4`)
+/// or given as the next argument (`-j 4`). Long options take their value as
+/// `--name=value` or `--name value`.
+fn tokenize(args: &[String]) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            // Everything after "--" is a pattern or a file name
+            tokens.extend(iter.by_ref().cloned().map(Token::Positional));
+        } else if let Some(long) = arg.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (format!("--{}", name), Some(value.to_string())),
+                None => (arg.clone(), None),
+            };
+
+            let value = match value {
+                None if LONG_WITH_VALUE.contains(&name.as_str()) => Some(
+                    iter.next()
+                        .with_context(|| format!("Option '{}' requires a value", name))?
+                        .clone(),
+                ),
+                value => value,
+            };
+
+            tokens.push(Token::Option(name, value));
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            for (i, c) in arg.char_indices().skip(1) {
+                if !SHORT_WITH_VALUE.contains(&c) {
+                    tokens.push(Token::Option(format!("-{}", c), None));
+                    continue;
+                }
+
+                // The rest of the argument, or the next one, is the value
+                let rest = &arg[i + c.len_utf8()..];
+                let value = if rest.is_empty() {
+                    iter.next()
+                        .with_context(|| format!("Option '-{}' requires a value", c))?
+                        .clone()
+                } else {
+                    rest.to_string()
+                };
+
+                tokens.push(Token::Option(format!("-{}", c), Some(value)));
+                break;
+            }
+        } else {
+            tokens.push(Token::Positional(arg.clone()));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parses command-line arguments
+///
+/// Usage: your_program [OPTIONS] <pattern> [FILE...]
+pub fn parse_args(args: &[String]) -> Result<Args> {
+    let mut syntax = Syntax::Basic;
+    let mut ascii = false;
+    let mut crlf = false;
+    let mut encoding = None;
+    let mut multiline = false;
+    let mut explain = None;
+    let mut trace = None;
+    let mut null_data = false;
+    let mut null = false;
+    let mut positional = vec![];
+    let mut binary_files = BinaryFiles::Binary;
+    let mut output = Output::Lines;
+    let mut max_count = None;
+    let mut replace = None;
+    let mut in_place = false;
+    let mut backup_suffix = None;
+    let mut dry_run = false;
+    let mut words = false;
+    let mut lines = false;
+    let mut no_messages = false;
+    let mut mmap = false;
+    let mut search_zip = false;
+    let mut pre = None;
+    let mut pre_glob = vec![];
+    let mut recursive = false;
+    let mut jobs = None;
+    let mut sorted = true;
+    let mut include = vec![];
+    let mut exclude = vec![];
+    let mut exclude_dir = vec![];
+    let mut ignore_files = false;
+
+    for token in tokenize(args)? {
+        let (name, value) = match token {
+            Token::Positional(arg) => {
+                positional.push(arg);
+                continue;
+            }
+            Token::Option(name, value) => (name, value),
+        };
+
+        match (name.as_str(), value.as_deref()) {
+            ("-G" | "--basic-regexp", None) => syntax = Syntax::Basic,
+            ("-E" | "--extended-regexp", None) => syntax = Syntax::Extended,
+            ("-P" | "--perl-regexp", None) => syntax = Syntax::Perl,
+            ("--ascii", None) => ascii = true,
+            ("--crlf", None) => crlf = true,
+            ("--encoding", Some("auto")) => encoding = None,
+            ("--encoding", Some(name)) => {
+                encoding = Some(
+                    Encoding::from_name(name)
+                        .with_context(|| format!("Unknown encoding '{}'", name))?,
+                )
+            }
+            ("-U" | "--multiline", None) => multiline = true,
+            ("--explain", None | Some("tree")) => explain = Some(Explain::Tree),
+            ("--explain", Some("program")) => explain = Some(Explain::Program),
+            ("--explain", Some(x)) => bail!("Unknown explain mode '{}'", x),
+            ("--trace", None) => trace = Some(trace::DEFAULT_LIMIT),
+            ("--trace", Some(value)) => {
+                trace = match value.parse() {
+                    Ok(0) | Err(_) => bail!("Invalid trace limit '{}'", value),
+                    Ok(n) => Some(n),
+                }
+            }
+            ("-z" | "--null-data", None) => null_data = true,
+            ("-Z" | "--null", None) => null = true,
+            ("-a" | "--text", None) => binary_files = BinaryFiles::Text,
+            ("--binary-files", Some(value)) => binary_files = parse_binary_files(value)?,
+            ("-l" | "--files-with-matches", None) => output = Output::FilesWithMatches,
+            ("-L" | "--files-without-match", None) => output = Output::FilesWithoutMatch,
+            ("-q" | "--quiet" | "--silent", None) => output = Output::Quiet,
+            ("--json", None) => output = Output::Json,
+            ("-m" | "--max-count", Some(value)) => {
+                max_count = Some(
+                    value
+                        .parse()
+                        .ok()
+                        .with_context(|| format!("Invalid max count '{}'", value))?,
+                )
+            }
+            ("--replace", Some(template)) => replace = Some(template.to_string()),
+            ("--in-place", suffix) => {
+                in_place = true;
+                backup_suffix = suffix.filter(|suffix| !suffix.is_empty()).map(String::from);
+            }
+            ("--dry-run", None) => dry_run = true,
+            ("-s" | "--no-messages", None) => no_messages = true,
+            ("-w" | "--word-regexp", None) => words = true,
+            ("-x" | "--line-regexp", None) => lines = true,
+            ("--mmap", None) => mmap = true,
+            ("--search-zip", None) => search_zip = true,
+            // An empty command turns off an earlier --pre
+            ("--pre", Some(command)) => pre = Some(command.to_string()).filter(|c| !c.is_empty()),
+            ("--pre-glob", Some(glob)) => pre_glob.push(glob.to_string()),
+            ("-r" | "--recursive", None) => recursive = true,
+            ("-j", Some(value)) => {
+                jobs = match value.parse() {
+                    Ok(0) | Err(_) => bail!("Invalid number of jobs '{}'", value),
+                    Ok(n) => Some(n),
+                }
+            }
+            ("--include", Some(glob)) => include.push(glob.to_string()),
+            ("--exclude", Some(glob)) => exclude.push(glob.to_string()),
+            ("--exclude-dir", Some(glob)) => exclude_dir.push(glob.to_string()),
+            ("--ignore-files", None) => ignore_files = true,
+            ("--sort", Some("path")) => sorted = true,
+            ("--sort", Some("none")) => sorted = false,
+            ("--sort", Some(x)) => bail!("Unknown sort order '{}'", x),
+            (_, Some(_)) => bail!("Option '{}' doesn't take a value", name),
+            _ => bail!("Unknown option '{}'", name),
+        }
+    }
+
+    if (in_place || dry_run) && replace.is_none() {
+        bail!("--in-place and --dry-run require --replace");
+    }
+
+    let mut positional = positional.into_iter();
+    Ok(Args {
+        pattern: positional.next().context("Pattern argument is required")?,
+        syntax,
+        ascii,
+        crlf,
+        encoding,
+        explain,
+        trace,
+        multiline,
+        null_data,
+        null,
+        files: positional.collect(),
+        binary_files,
+        output,
+        // Like GNU grep, -x wins over -w
+        span: match (lines, words) {
+            (true, _) => Span::Line,
+            (false, true) => Span::Word,
+            (false, false) => Span::Any,
+        },
+        max_count,
+        replace,
+        in_place,
+        backup_suffix,
+        dry_run,
+        no_messages,
+        mmap,
+        search_zip,
+        pre,
+        pre_glob,
+        recursive,
+        jobs: jobs.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get())),
+        include,
+        exclude,
+        exclude_dir,
+        ignore_files,
+        sorted,
+    })
+}
+
+fn parse_binary_files(value: &str) -> Result<BinaryFiles> {
+    Ok(match value {
+        "binary" => BinaryFiles::Binary,
+        "text" => BinaryFiles::Text,
+        "without-match" => BinaryFiles::WithoutMatch,
+        x => bail!("Unknown binary-files type '{}'", x),
+    })
+}
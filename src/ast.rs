@@ -0,0 +1,349 @@
+use crate::unicode::{self, Property};
+
+/// A parsed regular expression
+///
+/// This is the tree produced by the pattern parser. It is independent of the
+/// input encoding; the compiler decides how each node is matched against bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// Matches the empty string
+    Empty,
+    /// A single literal character
+    Literal(char),
+    /// `.` — any character except a line terminator
+    Wildcard,
+    /// A bracket expression or a shorthand class like `\d`
+    Class(Class),
+    /// `^` or `\A` — start of the input, or of the record when the matcher
+    /// has a line terminator
+    Start,
+    /// `$` or `\z` — end of the input, or of the record
+    End,
+    /// `^` in multi-line mode — start of the input or of any line in it
+    LineStart,
+    /// `$` in multi-line mode — end of the input or of any line in it
+    LineEnd,
+    /// `\Z` — end of the input, or right before a newline that ends it
+    EndBeforeNewline,
+    /// `\K` — drops everything matched so far from the reported match
+    ResetStart,
+    /// `\b` (or `\B` when negated), with ASCII or Unicode word characters
+    WordBoundary { negated: bool, ascii: bool },
+    /// `*`, `+`, `?` and `{min,max}` applied to a sub-pattern
+    Repeat {
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+        pattern: Box<Pattern>,
+    },
+    /// `(...)`, numbered from 1 in order of the opening parenthesis, and
+    /// named in Perl patterns spelled `(?<name>...)`
+    Group {
+        index: usize,
+        name: Option<String>,
+        pattern: Box<Pattern>,
+    },
+    /// `a|b|c`
+    Alternation(Vec<Pattern>),
+    /// A sequence of patterns matched one after the other
+    Concat(Vec<Pattern>),
+    /// `\1` to `\9`
+    Backreference(usize),
+}
+
+/// A set of characters, possibly negated
+#[derive(Debug, Clone, PartialEq)]
+pub struct Class {
+    pub negated: bool,
+    pub items: Vec<ClassItem>,
+    /// Whether the other case of a character counts as a member too
+    pub case_insensitive: bool,
+    /// Whether shorthands like `\d` and case folding only cover ASCII
+    pub ascii: bool,
+}
+
+/// One member of a character class
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClassItem {
+    Char(char),
+    Range(char, char),
+    Digit,
+    NotDigit,
+    Word,
+    NotWord,
+    Space,
+    NotSpace,
+    /// `\p{...}`
+    Property(Property),
+    /// `\P{...}`
+    NotProperty(Property),
+    /// `[:alpha:]` and the other POSIX classes of a bracket expression
+    Posix(PosixClass),
+}
+
+/// A named POSIX character class, like the `[:alpha:]` in `[[:alpha:]_]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PosixClass {
+    Alpha,
+    Digit,
+    Alnum,
+    Space,
+    Upper,
+    Lower,
+    Punct,
+    Xdigit,
+    Blank,
+    Cntrl,
+    Print,
+    Graph,
+}
+
+impl PosixClass {
+    const ALL: [PosixClass; 12] = [
+        PosixClass::Alpha,
+        PosixClass::Digit,
+        PosixClass::Alnum,
+        PosixClass::Space,
+        PosixClass::Upper,
+        PosixClass::Lower,
+        PosixClass::Punct,
+        PosixClass::Xdigit,
+        PosixClass::Blank,
+        PosixClass::Cntrl,
+        PosixClass::Print,
+        PosixClass::Graph,
+    ];
+
+    /// Looks up a class by the name between `[:` and `:]`
+    pub fn from_name(name: &str) -> Option<PosixClass> {
+        PosixClass::ALL
+            .into_iter()
+            .find(|class| class.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PosixClass::Alpha => "alpha",
+            PosixClass::Digit => "digit",
+            PosixClass::Alnum => "alnum",
+            PosixClass::Space => "space",
+            PosixClass::Upper => "upper",
+            PosixClass::Lower => "lower",
+            PosixClass::Punct => "punct",
+            PosixClass::Xdigit => "xdigit",
+            PosixClass::Blank => "blank",
+            PosixClass::Cntrl => "cntrl",
+            PosixClass::Print => "print",
+            PosixClass::Graph => "graph",
+        }
+    }
+
+    /// Checks if a character is in this class
+    ///
+    /// Like in GNU grep, `digit` and `xdigit` are always ASCII, while the
+    /// other classes follow Unicode unless `ascii` is set.
+    pub fn contains(&self, c: char, ascii: bool) -> bool {
+        if ascii || c.is_ascii() {
+            return ascii_class(*self, c);
+        }
+
+        let graph = !c.is_control() && !c.is_whitespace();
+        match self {
+            PosixClass::Alpha => c.is_alphabetic(),
+            PosixClass::Digit | PosixClass::Xdigit => false,
+            PosixClass::Alnum => c.is_alphanumeric(),
+            PosixClass::Space => c.is_whitespace(),
+            PosixClass::Upper => c.is_uppercase(),
+            PosixClass::Lower => c.is_lowercase(),
+            PosixClass::Punct => graph && !c.is_alphanumeric(),
+            PosixClass::Blank => {
+                c.is_whitespace() && !matches!(c, '\u{85}' | '\u{2028}' | '\u{2029}')
+            }
+            PosixClass::Cntrl => c.is_control(),
+            PosixClass::Print => graph || c.is_whitespace() && !c.is_control(),
+            PosixClass::Graph => graph,
+        }
+    }
+}
+
+fn ascii_class(class: PosixClass, c: char) -> bool {
+    match class {
+        PosixClass::Alpha => c.is_ascii_alphabetic(),
+        PosixClass::Digit => c.is_ascii_digit(),
+        PosixClass::Alnum => c.is_ascii_alphanumeric(),
+        PosixClass::Space => matches!(c, ' ' | '\t' | '\n' | '\x0B' | '\x0C' | '\r'),
+        PosixClass::Upper => c.is_ascii_uppercase(),
+        PosixClass::Lower => c.is_ascii_lowercase(),
+        PosixClass::Punct => c.is_ascii_punctuation(),
+        PosixClass::Xdigit => c.is_ascii_hexdigit(),
+        PosixClass::Blank => matches!(c, ' ' | '\t'),
+        PosixClass::Cntrl => c.is_ascii_control(),
+        PosixClass::Print => c.is_ascii_graphic() || c == ' ',
+        PosixClass::Graph => c.is_ascii_graphic(),
+    }
+}
+
+impl Class {
+    /// Builds a class holding a single shorthand item like `\d`
+    pub fn shorthand(item: ClassItem) -> Class {
+        Class {
+            negated: false,
+            items: vec![item],
+            case_insensitive: false,
+            ascii: false,
+        }
+    }
+
+    /// Builds a class matching a character in either case
+    pub fn caseless(c: char) -> Class {
+        Class {
+            negated: false,
+            items: vec![ClassItem::Char(c)],
+            case_insensitive: true,
+            ascii: false,
+        }
+    }
+
+    /// Checks if a character is a member of this class
+    pub fn contains(&self, c: char) -> bool {
+        let member = |c| self.items.iter().any(|item| item.contains(c, self.ascii));
+        let other_cases = if self.ascii {
+            [c.to_ascii_lowercase(), c.to_ascii_uppercase()]
+        } else {
+            [simple_lowercase(c), simple_uppercase(c)]
+        };
+
+        let found = member(c)
+            || (self.case_insensitive
+                && other_cases
+                    .into_iter()
+                    .any(|other| other != c && member(other)));
+
+        found != self.negated
+    }
+}
+
+impl ClassItem {
+    /// Checks if a character matches this class item
+    pub fn contains(&self, c: char, ascii: bool) -> bool {
+        match *self {
+            ClassItem::Char(x) => c == x,
+            ClassItem::Range(start, end) => start <= c && c <= end,
+            ClassItem::Digit => is_digit(c, ascii),
+            ClassItem::NotDigit => !is_digit(c, ascii),
+            ClassItem::Word => is_word(c, ascii),
+            ClassItem::NotWord => !is_word(c, ascii),
+            ClassItem::Space => is_space(c, ascii),
+            ClassItem::NotSpace => !is_space(c, ascii),
+            ClassItem::Property(property) => property.contains(c),
+            ClassItem::NotProperty(property) => !property.contains(c),
+            ClassItem::Posix(class) => class.contains(c, ascii),
+        }
+    }
+}
+
+/// `\d` — decimal digits, only `0` to `9` in ASCII mode
+pub fn is_digit(c: char, ascii: bool) -> bool {
+    if ascii || c.is_ascii() {
+        c.is_ascii_digit()
+    } else {
+        unicode::is_digit(c)
+    }
+}
+
+/// Lowercases a character, unless that takes more than one character
+fn simple_lowercase(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(x), None) => x,
+        _ => c,
+    }
+}
+
+/// Uppercases a character, unless that takes more than one character
+fn simple_uppercase(c: char) -> char {
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(x), None) => x,
+        _ => c,
+    }
+}
+
+/// `\w` — letters, digits and underscore
+///
+/// In Unicode mode this also covers marks and connector punctuation, as
+/// UTS #18 defines it.
+pub fn is_word(c: char, ascii: bool) -> bool {
+    if ascii || c.is_ascii() {
+        c.is_ascii_alphanumeric() || c == '_'
+    } else {
+        unicode::is_word(c)
+    }
+}
+
+/// `\s` — whitespace, only space and `\t\n\v\f\r` in ASCII mode
+pub fn is_space(c: char, ascii: bool) -> bool {
+    if ascii {
+        matches!(c, ' ' | '\t' | '\n' | '\x0B' | '\x0C' | '\r')
+    } else {
+        c.is_whitespace()
+    }
+}
+
+impl Pattern {
+    /// Checks if this pattern can succeed without consuming any input
+    ///
+    /// The compiler uses this to guard loops like `(a*)*` against spinning
+    /// forever on an empty iteration.
+    pub fn can_be_empty(&self) -> bool {
+        match self {
+            Pattern::Empty
+            | Pattern::Start
+            | Pattern::End
+            | Pattern::LineStart
+            | Pattern::LineEnd
+            | Pattern::EndBeforeNewline
+            | Pattern::ResetStart
+            | Pattern::WordBoundary { .. }
+            | Pattern::Backreference(_) => true,
+            Pattern::Literal(_) | Pattern::Wildcard | Pattern::Class(_) => false,
+            Pattern::Repeat { min, pattern, .. } => *min == 0 || pattern.can_be_empty(),
+            Pattern::Group { pattern, .. } => pattern.can_be_empty(),
+            Pattern::Alternation(branches) => branches.iter().any(Pattern::can_be_empty),
+            Pattern::Concat(items) => items.iter().all(Pattern::can_be_empty),
+        }
+    }
+
+    /// Returns the number of capture groups in this pattern
+    pub fn group_count(&self) -> usize {
+        match self {
+            Pattern::Group { index, pattern, .. } => (*index).max(pattern.group_count()),
+            Pattern::Repeat { pattern, .. } => pattern.group_count(),
+            Pattern::Alternation(items) | Pattern::Concat(items) => {
+                items.iter().map(Pattern::group_count).max().unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Records the name of every named group in this pattern by its index
+    pub fn group_names(&self, names: &mut [Option<String>]) {
+        match self {
+            Pattern::Group {
+                index,
+                name,
+                pattern,
+            } => {
+                names[*index] = name.clone();
+                pattern.group_names(names);
+            }
+            Pattern::Repeat { pattern, .. } => pattern.group_names(names),
+            Pattern::Alternation(items) | Pattern::Concat(items) => {
+                for item in items {
+                    item.group_names(names);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{ChildStdout, Command, Stdio};
+use std::thread;
+
+use crate::glob::Glob;
+use crate::walk;
+
+/// A command that turns files into the text to search (`--pre`)
+///
+/// It's run directly, not through a shell, with the path of the file as its
+/// only argument and the file on its stdin. Its output is searched instead of
+/// the file.
+pub struct Preprocessor {
+    pub program: String,
+    /// Only files matching one of these go through it, if there are any (`--pre-glob`)
+    pub globs: Vec<Glob>,
+}
+
+impl Preprocessor {
+    /// Checks if a file goes through the preprocessor
+    pub fn applies_to(&self, path: &str) -> bool {
+        self.globs.is_empty() || walk::matches_any(&self.globs, path)
+    }
+
+    /// Runs `search` over the preprocessed contents of a file
+    pub fn search<T>(
+        &self,
+        path: &str,
+        file: File,
+        search: impl FnOnce(&mut dyn Read) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let mut command = Command::new(&self.program);
+        command.arg(path);
+        search_output(command, file, search)
+    }
+}
+
+/// Runs `search` over the output of `command` reading `file` on its stdin
+///
+/// A failure is reported with the first line the command wrote to stderr.
+/// When `search` stops before the end of the output, like with `-l`, `-q` or
+/// `-m`, the command is killed instead of running to the end, and its exit
+/// status is ignored.
+pub fn search_output<T>(
+    mut command: Command,
+    file: File,
+    search: impl FnOnce(&mut dyn Read) -> io::Result<T>,
+) -> io::Result<T> {
+    let program = Path::new(command.get_program())
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let mut child = command
+        .stdin(file)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| io::Error::new(err.kind(), format!("can't run {}: {}", program, err)))?;
+
+    // Collect stderr on the side, so a chatty command can't block on it
+    let mut stderr = child.stderr.take().unwrap();
+    let errors = thread::spawn(move || {
+        let mut message = String::new();
+        stderr.read_to_string(&mut message).map(|_| message)
+    });
+
+    let mut stdout = Output {
+        stdout: child.stdout.take().unwrap(),
+        eof: false,
+    };
+    let result = search(&mut stdout);
+    let stopped = !stdout.eof;
+    if stopped {
+        // It may have exited already
+        let _ = child.kill();
+    }
+    drop(stdout);
+
+    let status = child.wait()?;
+    let message = errors.join().unwrap_or_else(|_| Ok(String::new()))?;
+
+    let value = result?;
+    if !status.success() && !stopped {
+        // Most tools start their messages with their own name
+        let prefix = format!("{}: ", program);
+        let message = message
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .map(|line| line.strip_prefix(&prefix).unwrap_or(line))
+            .unwrap_or("exited with an error");
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} failed: {}", program, message),
+        ));
+    }
+    Ok(value)
+}
+
+/// The output of a command, noting whether it was read to the end
+struct Output {
+    stdout: ChildStdout,
+    eof: bool,
+}
+
+impl Read for Output {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.stdout.read(buf)?;
+        self.eof |= len == 0 && !buf.is_empty();
+        Ok(len)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kills_the_command_when_the_search_stops() {
+        // `yes` never ends on its own
+        let command = Command::new("yes");
+        let file = File::open("/dev/null").unwrap();
+        let line = search_output(command, file, |reader| {
+            let mut line = [0; 2];
+            reader.read_exact(&mut line).map(|_| line)
+        });
+        assert_eq!(&line.unwrap(), b"y\n");
+    }
+
+    fn read_all(command: Command) -> io::Result<Vec<u8>> {
+        let file = File::open("/dev/null").unwrap();
+        search_output(command, file, |reader| {
+            let mut output = vec![];
+            reader.read_to_end(&mut output).map(|_| output)
+        })
+    }
+
+    #[test]
+    fn reports_failures_with_the_first_error_line() {
+        let mut command = Command::new("sh");
+        command.args([
+            "-c",
+            "echo out; printf '\\nsh: bad input\\nmore\\n' >&2; exit 3",
+        ]);
+        let error = read_all(command).unwrap_err();
+        assert_eq!(error.to_string(), "sh failed: bad input");
+
+        let mut command = Command::new("sh");
+        command.args(["-c", "exit 1"]);
+        let error = read_all(command).unwrap_err();
+        assert_eq!(error.to_string(), "sh failed: exited with an error");
+
+        let error = read_all(Command::new("no-such-preprocessor")).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("can't run no-such-preprocessor: "));
+    }
+
+    #[test]
+    fn applies_to_files_matching_its_globs() {
+        let all = Preprocessor {
+            program: "cat".to_string(),
+            globs: vec![],
+        };
+        assert!(all.applies_to("a.txt"));
+
+        let pdfs = Preprocessor {
+            program: "pdftotext".to_string(),
+            globs: vec![Glob::new("*.pdf").unwrap()],
+        };
+        assert!(pdfs.applies_to("docs/a.pdf"));
+        assert!(!pdfs.applies_to("a.txt"));
+    }
+}
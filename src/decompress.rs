@@ -0,0 +1,84 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::process::Command;
+
+use crate::command;
+
+/// Compressed formats, by the magic bytes they start with, and the program
+/// that decompresses them to stdout
+const FORMATS: &[(&[u8], &str)] = &[
+    (b"\x1f\x8b", "gzip"),
+    (b"BZh", "bzip2"),
+    (b"\xfd7zXZ\x00", "xz"),
+    (b"\x28\xb5\x2f\xfd", "zstd"),
+    (b"\x04\x22\x4d\x18", "lz4"),
+];
+
+/// Returns the decompressor for a file in a known compressed format
+///
+/// Only the first bytes of regular files are looked at, and the file is
+/// rewound afterwards. Pipes and special files are never decompressed.
+pub fn detect(file: &mut File) -> io::Result<Option<&'static str>> {
+    if !file.metadata()?.is_file() {
+        return Ok(None);
+    }
+
+    let mut magic = [0; 6];
+    let mut len = 0;
+    while len < magic.len() {
+        match file.read(&mut magic[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    file.seek(SeekFrom::Start(0))?;
+
+    Ok(FORMATS
+        .iter()
+        .find(|(prefix, _)| magic[..len].starts_with(prefix))
+        .map(|&(_, program)| program))
+}
+
+/// Runs `search` over the output of `program` decompressing `file`
+pub fn search<T>(
+    program: &str,
+    file: File,
+    search: impl FnOnce(&mut dyn Read) -> io::Result<T>,
+) -> io::Result<T> {
+    let mut command = Command::new(program);
+    command.arg("-dc");
+    command::search_output(command, file, search)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn reports_corrupt_archives() {
+        let path = std::env::temp_dir().join(format!("grep-corrupt-{}.gz", std::process::id()));
+        fs::write(&path, b"\x1f\x8bnot really gzip").unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        let program = detect(&mut file).unwrap();
+        assert_eq!(program, Some("gzip"));
+
+        let error = search("gzip", file, |reader| io::copy(reader, &mut io::sink())).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("gzip failed: "), "{}", error);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn leaves_other_files_alone() {
+        let path = std::env::temp_dir().join(format!("grep-plain-{}.gz", std::process::id()));
+        fs::write(&path, b"plain text").unwrap();
+        assert_eq!(detect(&mut File::open(&path).unwrap()).unwrap(), None);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,379 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Size of the chunks read from the input when transcoding
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A text encoding that inputs can be transcoded from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1, where every byte is the code point of the same value
+    Latin1,
+}
+
+impl Encoding {
+    /// Looks up an encoding by one of its usual names, ignoring case
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Encoding::Utf8,
+            // UTF-16 without a BOM is big-endian
+            "utf-16" | "utf16" | "utf-16be" | "utf16be" => Encoding::Utf16Be,
+            "utf-16le" | "utf16le" => Encoding::Utf16Le,
+            "latin1" | "latin-1" | "iso-8859-1" | "iso8859-1" => Encoding::Latin1,
+            _ => return None,
+        })
+    }
+
+    /// Returns the usual name of the encoding
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "Latin-1",
+        }
+    }
+
+    /// Returns the encoding announced by a byte order mark at the start of
+    /// `bytes`, and the length of the mark
+    fn from_bom(bytes: &[u8]) -> Option<(Encoding, usize)> {
+        match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => Some((Encoding::Utf8, 3)),
+            [0xFF, 0xFE, ..] => Some((Encoding::Utf16Le, 2)),
+            [0xFE, 0xFF, ..] => Some((Encoding::Utf16Be, 2)),
+            _ => None,
+        }
+    }
+
+    /// Returns the byte order mark of the encoding
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => &[0xEF, 0xBB, 0xBF],
+            Encoding::Utf16Le => &[0xFF, 0xFE],
+            Encoding::Utf16Be => &[0xFE, 0xFF],
+            Encoding::Latin1 => &[],
+        }
+    }
+}
+
+/// How a whole input was encoded, to write it back the same way
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Original {
+    encoding: Option<Encoding>,
+    bom: bool,
+}
+
+/// Transcodes a whole input to UTF-8 like a [`Decoder`] does, returning it
+/// with how it was encoded
+///
+/// Input that isn't valid in its encoding is rejected, since the U+FFFD that
+/// replaces an invalid sequence can't be encoded back into the same bytes.
+pub fn decode_all(input: &[u8], encoding: Option<Encoding>) -> io::Result<(Vec<u8>, Original)> {
+    let (encoding, body, bom) = match Encoding::from_bom(input) {
+        Some((encoding, len)) => (Some(encoding), &input[len..], true),
+        None => (encoding, input, false),
+    };
+    let original = Original { encoding, bom };
+
+    let Some(encoding) = encoding.filter(|&encoding| encoding != Encoding::Utf8) else {
+        return Ok((body.to_vec(), original));
+    };
+    let mut text = String::new();
+    decode(encoding, body, true, &mut text);
+    if encode(text.as_bytes(), original)? != input {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("not valid {}", encoding.name()),
+        ));
+    }
+    Ok((text.into_bytes(), original))
+}
+
+/// Encodes UTF-8 `text` the way `original` was, with its byte order mark
+pub fn encode(text: &[u8], original: Original) -> io::Result<Vec<u8>> {
+    let mut output = match (original.bom, original.encoding) {
+        (true, Some(encoding)) => encoding.bom().to_vec(),
+        _ => vec![],
+    };
+
+    let encoding = match original.encoding {
+        None | Some(Encoding::Utf8) => {
+            output.extend_from_slice(text);
+            return Ok(output);
+        }
+        Some(encoding) => encoding,
+    };
+    let text =
+        std::str::from_utf8(text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    for c in text.chars() {
+        match encoding {
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    output.extend_from_slice(&match encoding {
+                        Encoding::Utf16Le => unit.to_le_bytes(),
+                        _ => unit.to_be_bytes(),
+                    });
+                }
+            }
+            _ => match u8::try_from(c) {
+                Ok(b) => output.push(b),
+                Err(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{:?} can't be encoded in {}", c, encoding.name()),
+                    ))
+                }
+            },
+        }
+    }
+    Ok(output)
+}
+
+/// Checks if a file starts with a byte order mark, rewinding it afterwards
+pub fn has_bom(file: &mut File) -> io::Result<bool> {
+    let mut start = vec![];
+    file.take(3).read_to_end(&mut start)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(Encoding::from_bom(&start).is_some())
+}
+
+/// A reader that transcodes its input to UTF-8
+///
+/// A byte order mark at the start of the input decides the encoding and is
+/// dropped. Without one, the input is decoded from the given encoding, or
+/// passed through as it is when there's none. Invalid sequences become
+/// U+FFFD.
+pub struct Decoder<R> {
+    inner: R,
+    encoding: Option<Encoding>,
+    sniffed: bool,
+    /// Input read but not decoded yet: the start of the input while sniffing,
+    /// or an incomplete character
+    pending: Vec<u8>,
+    /// Decoded UTF-8 not returned yet, from `start`
+    decoded: Vec<u8>,
+    start: usize,
+    eof: bool,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(inner: R, encoding: Option<Encoding>) -> Decoder<R> {
+        Decoder {
+            inner,
+            encoding,
+            sniffed: false,
+            pending: vec![],
+            decoded: vec![],
+            start: 0,
+            eof: false,
+        }
+    }
+
+    /// Reads the first bytes of the input, looking for a byte order mark
+    fn sniff(&mut self) -> io::Result<()> {
+        while self.pending.len() < 3 {
+            let len = self.pending.len();
+            self.pending.resize(3, 0);
+            match self.inner.read(&mut self.pending[len..]) {
+                Ok(n) => {
+                    self.pending.truncate(len + n);
+                    if n == 0 {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    self.pending.truncate(len);
+                    if err.kind() != io::ErrorKind::Interrupted {
+                        return Err(err);
+                    }
+                }
+            }
+        }
+
+        if let Some((encoding, len)) = Encoding::from_bom(&self.pending) {
+            self.encoding = Some(encoding);
+            self.pending.drain(..len);
+        }
+        self.sniffed = true;
+        Ok(())
+    }
+
+    /// Decodes the next chunk of input into `decoded`
+    fn fill(&mut self, encoding: Encoding) -> io::Result<()> {
+        let len = self.pending.len();
+        self.pending.resize(len + CHUNK_SIZE, 0);
+        let n = loop {
+            match self.inner.read(&mut self.pending[len..]) {
+                Ok(n) => break n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    self.pending.truncate(len);
+                    return Err(err);
+                }
+            }
+        };
+        self.pending.truncate(len + n);
+        self.eof = n == 0;
+
+        let mut text = String::new();
+        let used = decode(encoding, &self.pending, self.eof, &mut text);
+        self.pending.drain(..used);
+
+        self.decoded = text.into_bytes();
+        self.start = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.sniffed {
+            self.sniff()?;
+        }
+
+        // UTF-8 only needs its byte order mark dropped
+        let encoding = match self.encoding {
+            None | Some(Encoding::Utf8) if !self.pending.is_empty() => {
+                let n = buf.len().min(self.pending.len());
+                buf[..n].copy_from_slice(&self.pending[..n]);
+                self.pending.drain(..n);
+                return Ok(n);
+            }
+            None | Some(Encoding::Utf8) => return self.inner.read(buf),
+            Some(encoding) => encoding,
+        };
+
+        while self.start == self.decoded.len() {
+            if self.eof {
+                return Ok(0);
+            }
+            self.fill(encoding)?;
+        }
+
+        let n = buf.len().min(self.decoded.len() - self.start);
+        buf[..n].copy_from_slice(&self.decoded[self.start..self.start + n]);
+        self.start += n;
+        Ok(n)
+    }
+}
+
+/// Decodes as much of `bytes` as forms complete characters into `text`,
+/// returning how many bytes were used
+///
+/// At the end of the input, a trailing incomplete character becomes U+FFFD.
+fn decode(encoding: Encoding, bytes: &[u8], eof: bool, text: &mut String) -> usize {
+    match encoding {
+        Encoding::Utf8 => unreachable!("UTF-8 is passed through"),
+        Encoding::Latin1 => {
+            text.extend(bytes.iter().map(|&b| b as char));
+            bytes.len()
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let unit = |pair: &[u8]| match encoding {
+                Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                _ => u16::from_be_bytes([pair[0], pair[1]]),
+            };
+            let mut units: Vec<u16> = bytes.chunks_exact(2).map(unit).collect();
+
+            // Keep a high surrogate whose pair isn't read yet for the next chunk
+            let mut used = units.len() * 2;
+            if !eof && units.last().is_some_and(|u| (0xD800..0xDC00).contains(u)) {
+                units.pop();
+                used -= 2;
+            }
+
+            text.extend(
+                char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)),
+            );
+            if eof && used < bytes.len() {
+                text.push(char::REPLACEMENT_CHARACTER);
+                used = bytes.len();
+            }
+            used
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(input: &[u8], encoding: Option<Encoding>) -> Vec<u8> {
+        let (text, original) = decode_all(input, encoding).unwrap();
+        encode(&text, original).unwrap()
+    }
+
+    #[test]
+    fn decodes_and_encodes_whole_inputs() {
+        let (text, _) = decode_all(b"\xFF\xFEh\x00\xE9\x00", None).unwrap();
+        assert_eq!(text, "hé".as_bytes());
+        let (text, _) = decode_all(b"caf\xE9", Some(Encoding::Latin1)).unwrap();
+        assert_eq!(text, "café".as_bytes());
+
+        for input in [
+            &b"\xFF\xFEh\x00\xE9\x00"[..],
+            b"\xFE\xFF\x00h\x00\xE9",
+            b"\xEF\xBB\xBFh\xC3\xA9",
+            b"plain",
+        ] {
+            assert_eq!(round_trip(input, None), input);
+        }
+        assert_eq!(round_trip(b"caf\xE9", Some(Encoding::Latin1)), b"caf\xE9");
+    }
+
+    #[test]
+    fn rejects_what_cant_be_written_back() {
+        // A lone high surrogate
+        let err = decode_all(b"\xFF\xFE\x00\xD8", None).unwrap_err();
+        assert_eq!(err.to_string(), "not valid UTF-16LE");
+
+        let (_, original) = decode_all(b"", Some(Encoding::Latin1)).unwrap();
+        let err = encode("€".as_bytes(), original).unwrap_err();
+        assert_eq!(err.to_string(), "'€' can't be encoded in Latin-1");
+    }
+
+    /// Returns its input a few bytes per read
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(self.1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    fn decode_reads(input: &[u8], read_size: usize, encoding: Option<Encoding>) -> String {
+        let mut text = String::new();
+        Decoder::new(Trickle(input, read_size), encoding)
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn joins_surrogate_pairs_split_across_reads() {
+        // U+1F600 is the pair D83D DE00
+        let input = b"\xFF\xFEa\x00\x3D\xD8\x00\xDEb\x00";
+        for read_size in 1..input.len() {
+            assert_eq!(decode_reads(input, read_size, None), "a\u{1F600}b");
+        }
+        // A high surrogate cut off by the end of the input
+        assert_eq!(decode_reads(b"\xFE\xFF\xD8\x3D", 1, None), "\u{FFFD}");
+    }
+
+    #[test]
+    fn decodes_latin1_without_a_bom() {
+        let input = b"caf\xE9 \xBD";
+        assert_eq!(decode_reads(input, 2, Some(Encoding::Latin1)), "café ½");
+        // A byte order mark wins over the given encoding
+        assert_eq!(
+            decode_reads(b"\xEF\xBB\xBFcaf\xC3\xA9", 2, Some(Encoding::Latin1)),
+            "café"
+        );
+    }
+}
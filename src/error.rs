@@ -0,0 +1,71 @@
+/// A syntax error in a pattern
+///
+/// Every variant carries the offset, in characters, of the part of the
+/// pattern that caused it.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum PatternError {
+    #[error("unclosed group")]
+    UnclosedGroup { offset: usize },
+    #[error("unclosed character class")]
+    UnclosedClass { offset: usize },
+    #[error("quantifier without anything to repeat")]
+    DanglingQuantifier { offset: usize },
+    #[error("interval without a valid closing brace")]
+    BadInterval { offset: usize },
+    #[error("unknown group type or inline flag")]
+    UnknownGroup { offset: usize },
+    #[error("invalid or duplicate group name")]
+    BadGroupName { offset: usize },
+    #[error("unknown Unicode property")]
+    UnknownProperty { offset: usize },
+    #[error("invalid escape sequence")]
+    BadEscape { offset: usize },
+    #[error("unknown POSIX class name")]
+    UnknownPosixClass { offset: usize },
+    #[error("repetition count is larger than 32767")]
+    RepeatTooLarge { offset: usize },
+    #[error("pattern is too large to compile")]
+    TooLarge { offset: usize },
+    #[error("invalid range, the end is before the start")]
+    InvalidRange { offset: usize },
+    #[error("backreference to a group that isn't defined before it")]
+    BadBackreference { offset: usize },
+}
+
+impl PatternError {
+    /// Returns the character offset of the error in the pattern
+    pub fn offset(&self) -> usize {
+        match *self {
+            PatternError::UnclosedGroup { offset }
+            | PatternError::UnclosedClass { offset }
+            | PatternError::DanglingQuantifier { offset }
+            | PatternError::BadInterval { offset }
+            | PatternError::UnknownGroup { offset }
+            | PatternError::BadGroupName { offset }
+            | PatternError::UnknownProperty { offset }
+            | PatternError::BadEscape { offset }
+            | PatternError::UnknownPosixClass { offset }
+            | PatternError::RepeatTooLarge { offset }
+            | PatternError::TooLarge { offset }
+            | PatternError::InvalidRange { offset }
+            | PatternError::BadBackreference { offset } => offset,
+        }
+    }
+
+    /// Renders the error below the pattern, with a caret under the offending position
+    ///
+    /// ```text
+    /// regex parse error:
+    ///     a(b
+    ///      ^
+    /// error: unclosed group
+    /// ```
+    pub fn render(&self, pattern: &str) -> String {
+        format!(
+            "regex parse error:\n    {}\n    {}^\nerror: {}",
+            pattern,
+            " ".repeat(self.offset()),
+            self
+        )
+    }
+}
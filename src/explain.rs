@@ -0,0 +1,295 @@
+//! Human-readable dumps of parsed patterns and compiled programs
+//!
+//! These back `grep --explain`, to show how a pattern was understood when it
+//! doesn't match as expected.
+
+use std::fmt::Write as _;
+
+use crate::ast::{Class, ClassItem, Pattern};
+use crate::program::{Inst, Origin, Program};
+
+/// Renders a pattern tree, one node per line, with children indented below
+/// their parent
+///
+/// Groups show their capture number and name, quantifiers their bounds and
+/// greediness, and class members the code points they cover.
+pub fn pattern_tree(pattern: &Pattern) -> String {
+    let mut tree = String::new();
+    write_node(&mut tree, pattern, 0);
+    tree
+}
+
+/// Renders a program as a numbered list of instructions
+pub fn program_listing(program: &Program) -> String {
+    let mut listing = String::new();
+    let width = program.insts.len().saturating_sub(1).to_string().len();
+
+    for (pc, inst) in program.insts.iter().enumerate() {
+        write!(listing, "{:>width$}  {}", pc, instruction(inst, program)).unwrap();
+        match &program.origins[pc] {
+            Some(origin) => writeln!(listing, " ({})", split_origin(origin)),
+            None => writeln!(listing),
+        }
+        .unwrap();
+    }
+    listing
+}
+
+fn write_node(tree: &mut String, pattern: &Pattern, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let line = match pattern {
+        Pattern::Empty => "empty".to_string(),
+        Pattern::Literal(c) => format!("literal {}", character(*c)),
+        Pattern::Wildcard => "any character".to_string(),
+        Pattern::Class(class) => {
+            writeln!(tree, "{}class{}", indent, class_flags(class, true)).unwrap();
+            for item in &class.items {
+                writeln!(tree, "{}  {}", indent, class_item(item)).unwrap();
+            }
+            return;
+        }
+        Pattern::Start => "start of input".to_string(),
+        Pattern::End => "end of input".to_string(),
+        Pattern::LineStart => "start of line".to_string(),
+        Pattern::LineEnd => "end of line".to_string(),
+        Pattern::EndBeforeNewline => "end of input, or before a final newline".to_string(),
+        Pattern::ResetStart => "reset match start".to_string(),
+        Pattern::WordBoundary { negated, ascii } => format!(
+            "{}word boundary{}",
+            if *negated { "not a " } else { "" },
+            if *ascii { " (ASCII)" } else { "" }
+        ),
+        Pattern::Repeat {
+            min,
+            max,
+            greedy,
+            pattern,
+        } => {
+            let bounds = match max {
+                Some(max) if max == min => format!("{{{}}}", min),
+                Some(max) => format!("{{{},{}}}", min, max),
+                None => format!("{{{},}}", min),
+            };
+            let kind = if *greedy { "greedy" } else { "lazy" };
+            writeln!(tree, "{}repeat {} {}", indent, bounds, kind).unwrap();
+            write_node(tree, pattern, depth + 1);
+            return;
+        }
+        Pattern::Group {
+            index,
+            name,
+            pattern,
+        } => {
+            match name {
+                Some(name) => writeln!(tree, "{}group {} <{}>", indent, index, name),
+                None => writeln!(tree, "{}group {}", indent, index),
+            }
+            .unwrap();
+            write_node(tree, pattern, depth + 1);
+            return;
+        }
+        Pattern::Alternation(branches) => {
+            writeln!(tree, "{}alternation", indent).unwrap();
+            for (i, branch) in branches.iter().enumerate() {
+                writeln!(tree, "{}  branch {}", indent, i + 1).unwrap();
+                write_node(tree, branch, depth + 2);
+            }
+            return;
+        }
+        Pattern::Concat(items) => {
+            writeln!(tree, "{}concat", indent).unwrap();
+            for item in items {
+                write_node(tree, item, depth + 1);
+            }
+            return;
+        }
+        Pattern::Backreference(index) => format!("backreference to group {}", index),
+    };
+
+    writeln!(tree, "{}{}", indent, line).unwrap();
+}
+
+/// Describes how a class applies its members, leaving out negation unless
+/// `negation` is set
+fn class_flags(class: &Class, negation: bool) -> String {
+    let flags: Vec<&str> = [
+        (class.negated && negation, "negated"),
+        (class.case_insensitive, "case-insensitive"),
+        (class.ascii, "ASCII"),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .map(|&(_, name)| name)
+    .collect();
+
+    match flags.is_empty() {
+        true => String::new(),
+        false => format!(" ({})", flags.join(", ")),
+    }
+}
+
+fn class_item(item: &ClassItem) -> String {
+    match item {
+        ClassItem::Char(c) => character(*c),
+        ClassItem::Range(start, end) => format!(
+            "{:?}-{:?} (U+{:04X}-U+{:04X})",
+            start, end, *start as u32, *end as u32
+        ),
+        ClassItem::Digit => "\\d digit".to_string(),
+        ClassItem::NotDigit => "\\D not a digit".to_string(),
+        ClassItem::Word => "\\w word character".to_string(),
+        ClassItem::NotWord => "\\W not a word character".to_string(),
+        ClassItem::Space => "\\s whitespace".to_string(),
+        ClassItem::NotSpace => "\\S not whitespace".to_string(),
+        ClassItem::Property(property) => format!("\\p{{{}}}", property.name()),
+        ClassItem::NotProperty(property) => format!("\\P{{{}}}", property.name()),
+        ClassItem::Posix(class) => format!("[:{}:]", class.name()),
+    }
+}
+
+/// Shows a character quoted, with its code point
+fn character(c: char) -> String {
+    format!("{:?} (U+{:04X})", c, c as u32)
+}
+
+/// Shows a class on one line, like `[^a-z\d]`
+fn class_summary(class: &Class) -> String {
+    let mut summary = String::from(if class.negated { "[^" } else { "[" });
+
+    for item in &class.items {
+        match item {
+            ClassItem::Char(c) => summary.extend(c.escape_debug()),
+            ClassItem::Range(start, end) => {
+                write!(summary, "{}-{}", start.escape_debug(), end.escape_debug()).unwrap()
+            }
+            ClassItem::Digit => summary.push_str("\\d"),
+            ClassItem::NotDigit => summary.push_str("\\D"),
+            ClassItem::Word => summary.push_str("\\w"),
+            ClassItem::NotWord => summary.push_str("\\W"),
+            ClassItem::Space => summary.push_str("\\s"),
+            ClassItem::NotSpace => summary.push_str("\\S"),
+            ClassItem::Property(property) => write!(summary, "\\p{{{}}}", property.name()).unwrap(),
+            ClassItem::NotProperty(property) => {
+                write!(summary, "\\P{{{}}}", property.name()).unwrap()
+            }
+            ClassItem::Posix(class) => write!(summary, "[:{}:]", class.name()).unwrap(),
+        }
+    }
+
+    summary.push(']');
+    summary + &class_flags(class, false)
+}
+
+/// Describes one instruction of a program
+pub fn instruction(inst: &Inst, program: &Program) -> String {
+    match inst {
+        Inst::Literal(c) => format!("char {}", character(*c)),
+        Inst::Any => "any".to_string(),
+        Inst::Class(class) => format!("class {}", class_summary(class)),
+        Inst::Start => "start".to_string(),
+        Inst::End => "end".to_string(),
+        Inst::LineStart => "line-start".to_string(),
+        Inst::LineEnd => "line-end".to_string(),
+        Inst::EndBeforeNewline => "end-before-newline".to_string(),
+        Inst::WordBoundary { negated, ascii } => format!(
+            "{}{}",
+            if *negated {
+                "not-word-boundary"
+            } else {
+                "word-boundary"
+            },
+            if *ascii { " (ASCII)" } else { "" }
+        ),
+        Inst::Split(first, second) => format!("split {}, {}", first, second),
+        Inst::Jump(target) => format!("jump {}", target),
+        // Slots past those of the groups are loop progress markers
+        Inst::Save(slot) if slot / 2 <= program.groups => format!(
+            "save {} (group {} {})",
+            slot,
+            slot / 2,
+            if slot % 2 == 0 { "start" } else { "end" }
+        ),
+        Inst::Save(slot) => format!("save {} (loop start)", slot),
+        Inst::Progress(slot) => format!("progress {}", slot),
+        Inst::Backreference(index) => format!("backreference {}", index),
+        Inst::Match => "match".to_string(),
+    }
+}
+
+/// Describes what a split was compiled from, like `branch 1 of 3` or
+/// `greedy repeat of 2..5`
+pub fn split_origin(origin: &Origin) -> String {
+    match origin {
+        Origin::Branch { index, count } => format!("branch {} of {}", index, count),
+        Origin::Repeat { greedy, body } => format!(
+            "{} repeat of {}..{}",
+            if *greedy { "greedy" } else { "lazy" },
+            body.start,
+            body.end
+        ),
+    }
+}
+
+/// Describes the path a split takes, its first target if `first` is set and
+/// its second one otherwise
+pub fn split_path(origin: &Origin, first: bool) -> String {
+    match origin {
+        Origin::Branch { index, count } => {
+            format!(
+                "branch {} of {}",
+                if first { *index } else { index + 1 },
+                count
+            )
+        }
+        // A greedy split runs the body first, a lazy one skips it first
+        Origin::Repeat { greedy, .. } if first == *greedy => {
+            format!("another run of {}", split_origin(origin))
+        }
+        Origin::Repeat { .. } => format!("past {}", split_origin(origin)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{self, Syntax};
+    use crate::program::Config;
+    use crate::Regex;
+
+    #[test]
+    fn renders_pattern_trees() {
+        let config = Config {
+            syntax: Syntax::Perl,
+            ..Config::default()
+        };
+        let pattern = parser::parse(r"(?<x>a|[^b-d\d])+?", &config).unwrap();
+        assert_eq!(
+            pattern_tree(&pattern),
+            "repeat {1,} lazy\n\
+             \x20 group 1 <x>\n\
+             \x20   alternation\n\
+             \x20     branch 1\n\
+             \x20       literal 'a' (U+0061)\n\
+             \x20     branch 2\n\
+             \x20       class (negated)\n\
+             \x20         'b'-'d' (U+0062-U+0064)\n\
+             \x20         \\d digit\n"
+        );
+    }
+
+    #[test]
+    fn lists_programs() {
+        let regex = Regex::new("a|b?").unwrap();
+        assert_eq!(
+            program_listing(regex.program()),
+            "0  save 0 (group 0 start)\n\
+             1  split 2, 4 (branch 1 of 2)\n\
+             2  char 'a' (U+0061)\n\
+             3  jump 6\n\
+             4  split 5, 6 (greedy repeat of 5..6)\n\
+             5  char 'b' (U+0062)\n\
+             6  save 1 (group 0 end)\n\
+             7  match\n"
+        );
+    }
+}
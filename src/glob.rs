@@ -0,0 +1,161 @@
+use codecrafters_grep::{Config, Error, Regex, Syntax};
+
+/// A shell-style wildcard pattern, like `*.rs` or `src/**/test_?.c`
+///
+/// Globs are translated into Perl-compatible regular expressions and run by
+/// the project's own engine. `*` and `?` don't match a `/`, while `**` does, so
+/// `a/**/b` matches `a/b` as well as `a/x/y/b`.
+pub struct Glob {
+    regex: Regex,
+}
+
+impl Glob {
+    pub fn new(glob: &str) -> Result<Glob, Error> {
+        // Only Perl brackets let a backslash escape `]` and the like
+        let config = Config {
+            syntax: Syntax::Perl,
+            ..Config::default()
+        };
+        Ok(Glob {
+            regex: Regex::with_config(&to_pattern(glob), config)?,
+        })
+    }
+
+    /// Checks if the glob matches all of `text`
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+}
+
+/// Translates a glob into an anchored Perl-compatible regular expression
+fn to_pattern(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    // `.` matches newlines in file names too
+    let mut pattern = String::from(r"(?s)\A");
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                // `**/` matches any number of directories, a trailing `**` everything
+                if chars.get(i + 2) == Some(&'/') {
+                    pattern.push_str("(.*/)?");
+                    i += 3;
+                } else {
+                    pattern.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            '[' => match class(&chars[i + 1..]) {
+                Some((class, len)) => {
+                    pattern.push_str(&class);
+                    i += len + 1;
+                    continue;
+                }
+                None => push_literal(&mut pattern, '['),
+            },
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                push_literal(&mut pattern, chars[i]);
+            }
+            c => push_literal(&mut pattern, c),
+        }
+        i += 1;
+    }
+
+    pattern.push_str(r"\z");
+    pattern
+}
+
+/// Translates a bracket expression, given the characters after its `[`
+///
+/// Returns the class and the number of characters it used, including the
+/// closing `]`, or `None` when it isn't closed.
+fn class(chars: &[char]) -> Option<(String, usize)> {
+    let mut class = String::from("[");
+    let mut i = 0;
+
+    if matches!(chars.first(), Some('!' | '^')) {
+        class.push('^');
+        i += 1;
+    }
+
+    // A ']' right after the opening bracket is a member
+    let first = i;
+    loop {
+        match *chars.get(i)? {
+            ']' if i > first => break,
+            '-' => class.push('-'),
+            '\\' => {
+                i += 1;
+                push_class_literal(&mut class, *chars.get(i)?);
+            }
+            c => push_class_literal(&mut class, c),
+        }
+        i += 1;
+    }
+
+    class.push(']');
+    Some((class, i + 1))
+}
+
+/// Adds a character that matches itself, escaping regex operators
+fn push_literal(pattern: &mut String, c: char) {
+    if !c.is_alphanumeric() && c.is_ascii() {
+        pattern.push('\\');
+    }
+    pattern.push(c);
+}
+
+/// Adds a class member that matches itself, escaping what a class treats specially
+fn push_class_literal(class: &mut String, c: char) {
+    if matches!(c, '\\' | ']' | '[' | '^' | '-') {
+        class.push('\\');
+    }
+    class.push(c);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(glob: &str, text: &str) -> bool {
+        Glob::new(glob).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn wildcards_stay_within_a_directory() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(!matches("*.rs", "src/main.rs"));
+        assert!(!matches("*.rs", "main.rs.bak"));
+        assert!(matches("test_?.c", "test_1.c"));
+        assert!(!matches("test_?.c", "test_/.c"));
+    }
+
+    #[test]
+    fn double_star_spans_directories() {
+        assert!(matches("src/**/*.rs", "src/main.rs"));
+        assert!(matches("src/**/*.rs", "src/parser/pcre.rs"));
+        assert!(!matches("src/**/*.rs", "tests/main.rs"));
+        assert!(matches("target/**", "target/debug/build"));
+    }
+
+    #[test]
+    fn brackets_and_escapes() {
+        assert!(matches("[abc].txt", "b.txt"));
+        assert!(!matches("[!abc].txt", "b.txt"));
+        assert!(matches("[a-c]x", "bx"));
+        assert!(matches("[]]", "]"));
+        assert!(matches(r"[\]]", "]"));
+        assert!(!matches(r"[\]]", "\\"));
+        assert!(matches(r"\*.txt", "*.txt"));
+        assert!(!matches(r"\*.txt", "a.txt"));
+        // An unclosed bracket is literal
+        assert!(matches("[a", "[a"));
+        // Regex operators in the glob are literal
+        assert!(matches("a+(b).c", "a+(b).c"));
+    }
+}
@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::glob::Glob;
+
+/// Ignore files read in every directory, from lowest to highest precedence
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+/// The rules of one ignore file, like a `.gitignore`
+///
+/// Patterns follow the gitignore format: a pattern with a `/` before its end
+/// is relative to the directory holding the file, others match a name at any
+/// depth below it. A trailing `/` only matches directories, and a leading `!`
+/// re-includes what an earlier pattern ignored.
+pub struct Ignore {
+    /// Absolute path of the directory the patterns are relative to
+    base: PathBuf,
+    rules: Vec<Rule>,
+}
+
+struct Rule {
+    glob: Glob,
+    negated: bool,
+    dir_only: bool,
+    /// Whether the glob matches the path relative to the base, or just the name
+    anchored: bool,
+}
+
+impl Ignore {
+    /// Reads an ignore file, returning `None` if it's missing or has no rules
+    pub fn read(file: &Path, base: &Path) -> Option<Ignore> {
+        let text = fs::read_to_string(file).ok()?;
+        let rules: Vec<Rule> = text.lines().filter_map(Rule::parse).collect();
+
+        (!rules.is_empty()).then(|| Ignore {
+            base: base.to_path_buf(),
+            rules,
+        })
+    }
+
+    /// Reads the ignore files that apply to the entries of `dir`
+    ///
+    /// They're returned from lowest to highest precedence: the repository's
+    /// `.git/info/exclude`, then `.gitignore` and `.ignore`.
+    pub fn read_dir(dir: &Path) -> Vec<Ignore> {
+        let exclude = dir.join(".git/info/exclude");
+        std::iter::once(exclude)
+            .chain(IGNORE_FILES.iter().map(|name| dir.join(name)))
+            .filter_map(|file| Ignore::read(&file, dir))
+            .collect()
+    }
+
+    /// Reads the ignore files of the directories above `dir`, up to the root
+    /// of the git repository it's in
+    ///
+    /// Outside of a repository, only ignore files below `dir` apply.
+    pub fn read_parents(dir: &Path) -> Vec<Ignore> {
+        let Ok(dir) = dir.canonicalize() else {
+            return vec![];
+        };
+
+        // The directory is the root of a repository itself
+        if dir.join(".git").exists() {
+            return vec![];
+        }
+
+        let parents: Vec<&Path> = dir.ancestors().skip(1).collect();
+        let Some(root) = parents
+            .iter()
+            .position(|parent| parent.join(".git").exists())
+        else {
+            return vec![];
+        };
+
+        // Outer directories go first, so deeper ones take precedence
+        parents[..=root]
+            .iter()
+            .rev()
+            .flat_map(|parent| Ignore::read_dir(parent))
+            .collect()
+    }
+
+    /// Decides if an entry is ignored: `Some(true)` if it is, `Some(false)` if
+    /// a negated pattern re-includes it, and `None` if no rule matches it
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.base).ok()?.to_string_lossy();
+        let name = path.file_name()?.to_string_lossy();
+
+        // The last matching rule wins
+        self.rules.iter().rev().find_map(|rule| {
+            let target = if rule.anchored { &relative } else { &name };
+            (rule.glob.is_match(target) && (is_dir || !rule.dir_only)).then_some(!rule.negated)
+        })
+    }
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Rule> {
+        // Trailing spaces are dropped unless escaped with a backslash
+        let mut line = line.trim_end_matches('\r');
+        if !line.ends_with("\\ ") {
+            line = line.trim_end_matches(' ');
+        }
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+
+        Some(Rule {
+            glob: Glob::new(line).ok()?,
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+}
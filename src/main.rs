@@ -1,338 +1,542 @@
+mod args;
+mod command;
+mod decompress;
+mod encoding;
+mod glob;
+mod ignore;
+mod json;
+mod mmap;
+mod parallel;
+mod rewrite;
+mod searcher;
+mod trace;
+mod walk;
+
 use std::env;
-use std::io;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
 use std::process;
-
-/// Checks if a character matches any of the given conditions
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use codecrafters_grep::program::Program;
+use codecrafters_grep::replace::Template;
+use codecrafters_grep::{explain, parser, Config, Regex};
+
+use crate::args::Explain;
+use crate::command::Preprocessor;
+use crate::encoding::Decoder;
+use crate::glob::Glob;
+use crate::rewrite::EditOptions;
+use crate::searcher::{BinaryFiles, Flow, Output, SearchOptions, Searcher, State};
+use crate::walk::{Entry, WalkOptions};
+
+/// Checks if the locale asks for UTF-8 aware matching
 ///
-/// This function is used to evaluate special character classes and literal characters
-/// in the regex pattern.
-fn match_next(next_char: char, conditions: &[String]) -> bool {
-    conditions.iter().any(|condition| match condition.as_str() {
-        "\\d" => next_char.is_digit(10),
-        "\\s" => next_char.is_whitespace(),
-        "\\w" => next_char.is_alphanumeric(),
-        "." => true,
-        x => next_char.to_string() == *x,
-    })
+/// Like GNU grep, the "C" and "POSIX" locales match raw bytes, so `.` matches
+/// any single byte. Every other locale decodes the input as UTF-8.
+fn unicode_locale() -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty());
+
+    !matches!(locale.as_deref(), Some("C" | "POSIX"))
+}
+
+/// Formats an I/O error the way GNU grep does, without the OS error code
+fn describe(err: &io::Error) -> String {
+    let message = err.to_string();
+    match message.find(" (os error") {
+        Some(i) => message[..i].to_string(),
+        None => message,
+    }
 }
 
-/// The main regex matching function
+/// Standard output, noting when whoever reads it went away
 ///
-/// This recursive function implements the core logic for matching a regex pattern
-/// against an input string. It handles various regex features like anchors,
-/// character classes, groups, and quantifiers.
-fn match_pattern(
-    input_line: &mut Vec<char>,
-    pattern: &mut Vec<char>,
-    must_match: bool,
-    backreferences: &mut Vec<Vec<char>>,
-    char_after_group: Option<&char>,
-) -> bool {
-    // If the input is empty, only match if the pattern ends with '$'
-    if input_line.is_empty() {
-        return pattern.first() == Some(&'$');
+/// Like GNU grep, the search then stops without a message. Something was
+/// printed by then, which means success in every output mode.
+struct Stdout<W> {
+    out: W,
+    closed: bool,
+}
+
+impl<W: Write> Stdout<W> {
+    fn new(out: W) -> Self {
+        Stdout { out, closed: false }
     }
 
-    let mut input_line_leftover = None;
-
-    // Handle different pattern start conditions
-    let (conditions, mut pattern_matched_last_index, is_negated) = match pattern.first() {
-        // Handle start-of-line anchor '^'
-        Some(&'^') if !must_match => {
-            return match_pattern(
-                input_line,
-                &mut pattern[1..].to_vec(),
-                true,
-                backreferences,
-                None,
-            )
+    fn check<T>(&mut self, result: io::Result<T>) -> io::Result<T> {
+        if let Err(err) = &result {
+            self.closed |= err.kind() == io::ErrorKind::BrokenPipe;
         }
-        // Handle escaped characters and backreferences
-        Some(&'\\') => {
-            if let Some(index) = pattern.get(1) {
-                if index.is_digit(10) {
-                    let backreference_index = index.to_digit(10).unwrap() as usize;
-                    if let Some(backreference) =
-                        backreferences.get(backreference_index.saturating_sub(1))
-                    {
-                        let pattern_leftover = if pattern.len() > 2 {
-                            pattern[2..].to_vec()
-                        } else {
-                            vec![]
-                        };
-
-                        let updated_pattern =
-                            vec![backreference.clone(), pattern_leftover].concat();
-
-                        return match_pattern(
-                            input_line,
-                            &mut updated_pattern.to_vec(),
-                            true,
-                            backreferences,
-                            char_after_group,
-                        );
-                    }
-                }
-            }
+        result
+    }
+}
 
-            let conditions = vec![pattern[0..=1].iter().collect::<String>()];
+impl<W: Write> Write for Stdout<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let result = self.out.write(buf);
+        self.check(result)
+    }
 
-            (conditions, 1usize, false)
-        }
-        // Handle character classes [...]
-        Some(&'[') => {
-            let mut conditions: Vec<String> = vec![];
-            let mut i: usize = 0;
-            let mut is_negated = false;
-
-            while i < pattern.len() {
-                i += 1;
-                if pattern[i] == ']' {
-                    break;
-                } else if pattern[i] == '^' {
-                    is_negated = true;
-                    continue;
-                }
+    fn flush(&mut self) -> io::Result<()> {
+        let result = self.out.flush();
+        self.check(result)
+    }
+}
 
-                let condition = if pattern[i] == '\\' {
-                    i += 1;
-                    pattern[i - 1..=i].iter().collect::<String>()
-                } else {
-                    pattern[i].to_string()
-                };
+/// The outcome of searching one input
+struct Report {
+    name: String,
+    result: io::Result<State>,
+}
 
-                conditions.push(condition);
-            }
+/// How each input is read
+struct InputOptions {
+    /// Search regular files through a memory map (`--mmap`)
+    mmap: bool,
+    /// Search the contents of compressed files (`--search-zip`)
+    search_zip: bool,
+    /// Search the output of a command instead of some files (`--pre`)
+    pre: Option<Preprocessor>,
+    /// Edit files with the replacements instead of printing lines
+    edit: Option<EditOptions>,
+}
 
-            (conditions, i, is_negated)
+/// Searches a single input, writing matching lines to `out`
+fn search_entry<W: Write>(
+    searcher: &mut Searcher,
+    entry: &Entry,
+    options: &InputOptions,
+    out: &mut W,
+) -> Report {
+    let (name, result) = match (entry, &options.edit) {
+        (Entry::Error(name, err), _) => (
+            name.as_str(),
+            Err(io::Error::new(err.kind(), err.to_string())),
+        ),
+        (Entry::File(file), Some(_)) if file == "-" => (
+            "(standard input)",
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "can't edit standard input in place",
+            )),
+        ),
+        (Entry::File(file), None) if file == "-" => (
+            "(standard input)",
+            searcher.search(io::stdin().lock(), "(standard input)", out),
+        ),
+        (Entry::File(file), Some(edit)) => {
+            (file.as_str(), rewrite::edit_file(searcher, file, edit, out))
         }
-        // Handle groups (...)
-        Some(&'(') => {
-            let mut i: usize = 1;
-            let mut current_pattern: Vec<char> = vec![];
-            let mut matched_line = None;
-            let mut internal_parentheses_starts = 0;
-            let mut internal_parentheses_ends = 0;
-
-            while i < pattern.len() {
-                if pattern[i] == '(' {
-                    internal_parentheses_starts += 1;
-                }
+        (Entry::File(file), None) => (file.as_str(), search_file(searcher, file, options, out)),
+    };
 
-                if internal_parentheses_starts == internal_parentheses_ends
-                    && (pattern[i] == ')' || pattern[i] == '|')
-                {
-                    if internal_parentheses_starts > 0 {
-                        backreferences.push(current_pattern.clone());
-                    }
-
-                    if input_line_leftover.is_none() {
-                        let mut input_line_clone = input_line.clone();
-
-                        let is_conditional = current_pattern.starts_with(&['[']);
-                        let char_after_group = if is_conditional {
-                            pattern.get(current_pattern.len() + 2)
-                        } else {
-                            None
-                        };
-
-                        let current_pattern_match = match_pattern(
-                            &mut input_line_clone,
-                            &mut current_pattern,
-                            must_match,
-                            backreferences,
-                            char_after_group,
-                        );
-
-                        if current_pattern_match {
-                            matched_line = Some(
-                                input_line[0..(input_line.len() - input_line_clone.len())].to_vec(),
-                            );
-                            input_line_leftover = Some(input_line_clone.clone());
-
-                            input_line.clear();
-                            input_line.extend(input_line_clone);
-                        }
-                    }
-
-                    current_pattern.clear();
-
-                    if pattern[i] == ')' {
-                        break;
-                    }
-                } else {
-                    current_pattern.push(pattern[i]);
+    // List the file name once its outcome is known
+    let result = result.and_then(|state| searcher.finish(name, &state, out).map(|_| state));
 
-                    if pattern[i] == ')' {
-                        internal_parentheses_ends += 1;
-                    }
-                }
+    Report {
+        name: name.to_string(),
+        result,
+    }
+}
 
-                i += 1;
-            }
+/// Searches a file, reading it as raw bytes so invalid UTF-8 doesn't abort
+/// the search
+fn search_file<W: Write>(
+    searcher: &mut Searcher,
+    file: &str,
+    options: &InputOptions,
+    out: &mut W,
+) -> io::Result<State> {
+    let mut handle = File::open(file)?;
+
+    // Matches in preprocessed or compressed files are reported under the
+    // file's own name
+    if let Some(pre) = options.pre.as_ref().filter(|pre| pre.applies_to(file)) {
+        return pre.search(file, handle, |reader| searcher.search(reader, file, out));
+    }
+    if options.search_zip {
+        if let Some(program) = decompress::detect(&mut handle)? {
+            return decompress::search(program, handle, |reader| {
+                searcher.search(reader, file, out)
+            });
+        }
+    }
 
-            if let Some(line) = input_line_leftover.as_mut() {
-                let mut next_patterns = pattern[i + 1..].to_vec();
-                backreferences.push(matched_line.unwrap());
-                if next_patterns.len() > 0 {
-                    let matched = match_pattern(
-                        line,
-                        &mut next_patterns,
-                        true,
-                        backreferences,
-                        char_after_group,
-                    );
-
-                    if matched {
-                        input_line.clear();
-                        input_line.extend(line.iter());
-                    }
-
-                    return matched;
-                } else {
-                    return true;
-                }
-            } else {
-                return false;
+    // Only files that need no transcoding can be searched in place
+    if options.mmap
+        && searcher.encoding().is_none()
+        && handle.metadata()?.is_file()
+        && !encoding::has_bom(&mut handle)?
+    {
+        mmap::search_file(searcher, &handle, file, out)
+    } else {
+        searcher.search(&handle, file, out)
+    }
+}
+
+/// Prints the steps the matcher takes on every line of the inputs, as
+/// `searcher` would search them
+///
+/// Inputs are transcoded like in a search, but never decompressed or
+/// preprocessed. Returns the exit status of the search the trace stands for.
+fn trace_entries(
+    searcher: &Searcher,
+    program: &Program,
+    entries: &[Entry],
+    limit: u64,
+) -> Result<i32> {
+    let encoding = searcher.encoding();
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut steps = 0;
+    let mut matched = false;
+    let mut had_error = false;
+
+    for entry in entries {
+        let (name, input) = match entry {
+            Entry::Error(name, err) => (
+                name.as_str(),
+                Err(io::Error::new(err.kind(), err.to_string())),
+            ),
+            Entry::File(file) if file == "-" => {
+                let mut input = vec![];
+                let result = Decoder::new(io::stdin().lock(), encoding).read_to_end(&mut input);
+                ("(standard input)", result.map(|_| input))
             }
-        }
-        // Handle literal characters
-        Some(x) => (vec![x.to_string()], 0usize, false),
-        // If pattern is empty, it's a match
-        None => {
-            return true;
-        }
-    };
+            Entry::File(file) => {
+                let mut input = vec![];
+                let result = File::open(file)
+                    .and_then(|file| Decoder::new(file, encoding).read_to_end(&mut input));
+                (file.as_str(), result.map(|_| input))
+            }
+        };
+
+        let input = match input {
+            Ok(input) => input,
+            Err(err) => {
+                out.flush()?;
+                eprintln!("grep: {}: {}", name, describe(&err));
+                had_error = true;
+                continue;
+            }
+        };
 
-    // Handle quantifiers and perform character matching
-    let (is_matching, input_line_matched_last_index, skip) = {
-        // Check for '+' and '?' quantifiers
-        let one_or_more = pattern
-            .get(pattern_matched_last_index + 1)
-            .map(|x| x == &'+')
-            .unwrap_or(false);
-        let zero_or_one = pattern
-            .get(pattern_matched_last_index + 1)
-            .map(|x| x == &'?')
-            .unwrap_or(false);
-
-        if one_or_more || zero_or_one {
-            pattern_matched_last_index += 1;
+        let (found, stopped) =
+            trace::trace_input(searcher, program, name, &input, limit, &mut steps, &mut out)?;
+        matched |= found;
+        if stopped {
+            break;
         }
+    }
 
-        // Perform character matching
-        let mut matches = 0;
-        while let Some(next_char) = input_line.get(matches) {
-            if match_next(*next_char, &conditions) {
-                // If negated and match, it's not a global matching
-                if is_negated {
-                    return false;
-                }
+    out.flush()?;
+    Ok(if had_error {
+        2
+    } else if matched {
+        0
+    } else {
+        1
+    })
+}
 
-                matches += 1;
+/// Exit status bookkeeping across all inputs
+#[derive(Default)]
+struct Summary {
+    matched: bool,
+    had_error: bool,
+    /// Don't report unreadable inputs (`-s`)
+    no_messages: bool,
+    /// A match makes the search succeed even after errors (`-q`)
+    quiet: bool,
+    /// Number of inputs searched, and of those with a match
+    searches: u64,
+    searches_with_match: u64,
+    /// Number of matching lines in all inputs
+    matched_lines: u64,
+}
 
-                if one_or_more {
-                    continue;
+impl Summary {
+    /// Prints the messages for one searched input and records its outcome
+    fn add(&mut self, report: Report) {
+        match report.result {
+            Ok(state) => {
+                if state.binary_matched {
+                    eprintln!("grep: {}: binary file matches", report.name);
                 }
-            } else if is_negated {
-                if let Some(char_after_group) = char_after_group {
-                    if *char_after_group == *next_char {
-                        break;
-                    }
+                self.matched |= state.matched;
+                self.searches += 1;
+                self.searches_with_match += state.matched as u64;
+                self.matched_lines += state.count;
+            }
+            Err(err) => {
+                if !self.no_messages {
+                    eprintln!("grep: {}: {}", report.name, describe(&err));
                 }
+                self.had_error = true;
+            }
+        }
+    }
 
-                matches += 1;
+    /// Checks if the remaining inputs can't change the outcome anymore
+    fn settled(&self) -> bool {
+        self.quiet && self.matched
+    }
 
-                if one_or_more {
-                    continue;
-                }
-            }
+    /// Returns the exit status: 0 if any line matched, 1 if none did and 2 if
+    /// an input couldn't be read
+    ///
+    /// Like GNU grep, a match with `-q` wins over errors.
+    fn status(&self) -> i32 {
+        if self.had_error && !self.settled() {
+            2
+        } else if self.matched {
+            0
+        } else {
+            1
+        }
+    }
+}
 
-            break;
+/// Parses arguments, compiles the pattern and searches every input
+fn run() -> Result<i32> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args = args::parse_args(&args)?;
+
+    // Outside of a UTF-8 locale, classes and case folding stay within ASCII
+    let unicode = unicode_locale();
+    let terminator = if args.null_data { b'\0' } else { b'\n' };
+    let config = Config {
+        unicode,
+        // Matches may span lines with -U, where `^` and `$` match at every line
+        line_terminator: (!args.multiline).then_some(terminator),
+        syntax: args.syntax,
+        multi_line: args.multiline,
+        crlf: args.crlf,
+        ascii: args.ascii || !unicode,
+    };
+    let regex = Regex::with_config(&args.pattern, config)?;
+    let program = regex.program();
+
+    if let Some(explain) = args.explain {
+        let pattern = parser::parse(&args.pattern, &config)?;
+        print!("{}", explain::pattern_tree(&pattern));
+        if explain == Explain::Program {
+            println!();
+            print!("{}", explain::program_listing(program));
         }
+        return Ok(0);
+    }
 
-        // Return matching results
-        (
-            matches > 0,
-            matches.saturating_sub(1),
-            zero_or_one && matches == 0,
-        )
+    let globs = |globs: &[String]| -> Result<Vec<Glob>> {
+        globs
+            .iter()
+            .map(|glob| Glob::new(glob).with_context(|| format!("Invalid glob '{}'", glob)))
+            .collect()
+    };
+    let walk_options = WalkOptions {
+        recursive: args.recursive,
+        sorted: args.sorted,
+        include: globs(&args.include)?,
+        exclude: globs(&args.exclude)?,
+        exclude_dir: globs(&args.exclude_dir)?,
+        ignore_files: args.ignore_files,
+    };
+    let entries = walk::collect(&args.files, &walk_options);
+
+    // Name files when there are several, or when searching a whole directory
+    let searches_directory = args.recursive
+        && (args.files.is_empty() || args.files.iter().any(|file| Path::new(file).is_dir()));
+    let options = SearchOptions {
+        // JSON keeps binary data intact by encoding it, so it's searched as text
+        binary_files: match (args.output, args.binary_files) {
+            (Output::Json, BinaryFiles::Binary) => BinaryFiles::Text,
+            (_, binary_files) => binary_files,
+        },
+        output: args.output,
+        span: args.span,
+        max_count: args.max_count,
+        with_filename: entries.len() > 1 || searches_directory,
+        terminator,
+        null_after_name: args.null,
+        replace: args
+            .replace
+            .as_deref()
+            .map(|template| Template::new(template, program)),
+        multiline: args.multiline,
+        encoding: args.encoding,
+        cancelled: Arc::new(AtomicBool::new(false)),
     };
 
-    // Remove matched characters from input, if a literal match happened
-    if !skip {
-        input_line.drain(0..=input_line_matched_last_index);
+    if let Some(limit) = args.trace {
+        let searcher = Searcher::new(program, options);
+        return trace_entries(&searcher, program, &entries, limit);
     }
 
-    // Handle successful matches and pattern updates
-    if is_matching || skip {
-        // Remove matched pattern
-        pattern.drain(0..=pattern_matched_last_index);
+    let input_options = InputOptions {
+        mmap: args.mmap,
+        search_zip: args.search_zip,
+        pre: match &args.pre {
+            Some(program) => Some(Preprocessor {
+                program: program.clone(),
+                globs: globs(&args.pre_glob)?,
+            }),
+            None => None,
+        },
+        edit: (args.in_place || args.dry_run).then(|| EditOptions {
+            backup_suffix: args.backup_suffix.clone(),
+            dry_run: args.dry_run,
+        }),
+    };
+
+    let mut summary = Summary {
+        no_messages: args.no_messages,
+        quiet: args.output == Output::Quiet,
+        ..Summary::default()
+    };
+    let mut out = Stdout::new(BufWriter::new(io::stdout().lock()));
+
+    if entries.len() <= 1 || args.jobs == 1 {
+        // Stream results straight to stdout
+        let mut searcher = Searcher::new(program, options);
+        for entry in &entries {
+            let report = search_entry(&mut searcher, entry, &input_options, &mut out);
+            let flushed = out.flush();
+            if out.closed {
+                return Ok(0);
+            }
+            flushed?;
+            summary.add(report);
+
+            if summary.settled() {
+                break;
+            }
+        }
+    } else {
+        // Buffer each file's output, so files never interleave
+        let mut write_result = Ok(());
+        parallel::for_each(
+            entries.len(),
+            args.jobs,
+            // With -q, the first match from any input settles the search
+            args.sorted && !summary.quiet,
+            |index| {
+                let mut searcher = Searcher::new(program, options.clone());
+                let mut output = vec![];
+                let report =
+                    search_entry(&mut searcher, &entries[index], &input_options, &mut output);
+                (output, report)
+            },
+            |(output, report)| {
+                if write_result.is_ok() {
+                    write_result = out.write_all(&output).and_then(|_| out.flush());
+                }
+                if !out.closed {
+                    summary.add(report);
+                }
 
-        // If no more pattern, it's a complete match
-        if pattern.len() == 0 {
-            return true;
+                if summary.settled() || out.closed {
+                    // Stop the inputs other jobs are still searching
+                    options.cancelled.store(true, Ordering::Relaxed);
+                    Flow::Stop
+                } else {
+                    Flow::Continue
+                }
+            },
+        );
+        if out.closed {
+            return Ok(0);
         }
-    // This match was mandatory but didn't happen
-    } else if must_match {
-        return false;
+        write_result?;
     }
 
-    // Handle negated character classes at the end of input
-    if is_negated && input_line.len() == 0 {
-        return true;
+    if args.output == Output::Json {
+        writeln!(
+            out,
+            "{{\"type\":\"summary\",\"data\":{{\"stats\":{{\"searches\":{},\"searches_with_match\":{},\"matched_lines\":{}}}}}}}",
+            summary.searches, summary.searches_with_match, summary.matched_lines
+        )?;
     }
-
-    // Recursively continue matching
-    match_pattern(
-        input_line,
-        pattern,
-        must_match || is_matching,
-        backreferences,
-        char_after_group,
-    )
+    out.flush()?;
+    Ok(summary.status())
 }
 
 /// The main function that parses command-line arguments and runs the regex matcher
 ///
-/// Usage: echo <input_text> | your_program -E <pattern>
+/// Usage: echo <input_text> | your_program -E <pattern> [FILE...]
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    // Exit with 0 if there's a match, 1 if there's no match and 2 on errors
+    process::exit(match run() {
+        Ok(status) => status,
+        Err(err) => {
+            eprintln!("grep: {:#}", err);
+            2
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pipe whose reader went away
+    struct Closed;
 
-    // Ensure the first argument is '-E'
-    if args.get(1).map_or(true, |arg| arg != "-E") {
-        eprintln!("Expected first argument to be '-E'");
-        process::exit(1);
+    impl Write for Closed {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
     }
 
-    // Get the regex pattern from command-line arguments
-    let pattern: Vec<char> = args
-        .get(2)
-        .expect("Pattern argument is required")
-        .chars()
-        .collect();
-
-    // Read input from stdin
-    let input_line: Vec<char> = io::stdin()
-        .lines()
-        .next()
-        .expect("Failed to read input")
-        .expect("Failed to parse input")
-        .chars()
-        .collect();
-
-    // Run the regex matcher and exit with appropriate status code
-    process::exit(
-        if match_pattern(
-            &mut input_line.clone(),
-            &mut pattern.clone(),
-            false,
-            &mut vec![],
-            None,
-        ) {
-            0 // Exit with 0 if there's a match
-        } else {
-            1 // Exit with 1 if there's no match
-        },
-    );
+    #[test]
+    fn notes_a_closed_stdout() {
+        let mut out = Stdout::new(vec![]);
+        out.write_all(b"line\n").unwrap();
+        assert!(!out.closed);
+
+        let mut out = Stdout::new(BufWriter::new(Closed));
+        out.write_all(b"line\n").unwrap();
+        assert!(!out.closed);
+        assert!(out.flush().is_err());
+        assert!(out.closed);
+    }
+
+    fn summary(quiet: bool, no_messages: bool, results: Vec<io::Result<bool>>) -> i32 {
+        let mut summary = Summary {
+            quiet,
+            no_messages,
+            ..Summary::default()
+        };
+        for result in results {
+            summary.add(Report {
+                name: "f".to_string(),
+                result: result.map(|matched| State {
+                    matched,
+                    ..State::default()
+                }),
+            });
+        }
+        summary.status()
+    }
+
+    #[test]
+    fn exit_statuses() {
+        let missing = || Err(io::ErrorKind::NotFound.into());
+        assert_eq!(summary(false, false, vec![Ok(true), Ok(false)]), 0);
+        assert_eq!(summary(false, false, vec![Ok(false)]), 1);
+        assert_eq!(summary(false, false, vec![Ok(true), missing()]), 2);
+        // -s hides the message, not the error
+        assert_eq!(summary(false, true, vec![Ok(true), missing()]), 2);
+        // With -q, a match wins over errors
+        assert_eq!(summary(true, true, vec![missing(), Ok(true)]), 0);
+        assert_eq!(summary(true, true, vec![missing(), Ok(false)]), 2);
+    }
 }
@@ -0,0 +1,479 @@
+use crate::ast::is_word;
+use crate::program::{Inst, Program};
+
+/// Capture slots of a match: the start and end offset of every group
+pub type Slots = Vec<Option<usize>>;
+
+/// Work items of the backtracking matcher
+enum Job {
+    /// Run the program from `pc` at input offset `pos`, as the alternative
+    /// that the split at `split` left for later
+    ///
+    /// The first path of an attempt isn't left by any split, and has `split`
+    /// set to 0, which is always a `Save`.
+    Step { pc: usize, pos: usize, split: usize },
+    /// Undo a slot write when backtracking past it
+    Restore { slot: usize, value: Option<usize> },
+}
+
+/// Most bits the matcher spends on remembering the states it tried, 32 MiB
+const MEMO_LIMIT: usize = 1 << 28;
+
+/// The states a matcher already tried, as bits per split and input position
+/// laid out by [`Program::memo`]
+///
+/// The bits are only
+/// allocated once a search ran more splits than there are bits, so searches
+/// that don't backtrack much never pay for them, and a search that does is
+/// bounded by the number of states instead of exponential.
+struct Memo {
+    bits: Vec<u64>,
+    /// Words with a bit set since the last `clear`
+    touched: Vec<usize>,
+    start: usize,
+    width: usize,
+    /// Number of bits, or 0 when the program can't be memoized
+    size: usize,
+    splits: usize,
+}
+
+impl Memo {
+    fn new(program: &Program, haystack: &[u8], start: usize) -> Memo {
+        let size = program
+            .memo_width
+            .saturating_mul(haystack.len() + 1 - start);
+        Memo {
+            bits: vec![],
+            touched: vec![],
+            start,
+            width: program.memo_width,
+            size: if size <= MEMO_LIMIT { size } else { 0 },
+            splits: 0,
+        }
+    }
+
+    /// Records a visit of a split at input offset `pos`, with `bit` from its
+    /// [`MemoIndex`](crate::program::MemoIndex), returning `false` if it was tried before
+    #[inline(always)]
+    fn visit(&mut self, pos: usize, bit: usize) -> bool {
+        if self.bits.is_empty() {
+            self.splits += 1;
+            if self.size == 0 || self.splits <= self.size {
+                return true;
+            }
+            self.bits = vec![0; self.size.div_ceil(64)];
+        }
+
+        let bit = (pos - self.start) * self.width + bit;
+        let word = &mut self.bits[bit / 64];
+        let mask = 1 << (bit % 64);
+        if *word & mask != 0 {
+            return false;
+        }
+        if *word == 0 {
+            self.touched.push(bit / 64);
+        }
+        *word |= mask;
+        true
+    }
+
+    /// Forgets every state tried so far
+    fn clear(&mut self) {
+        for word in self.touched.drain(..) {
+            self.bits[word] = 0;
+        }
+    }
+}
+
+/// Something the matcher reports its steps to, for debugging patterns
+pub trait Tracer {
+    /// Handles one event, returning `false` to stop the search
+    fn event(&mut self, event: Event) -> bool;
+}
+
+/// A step of the matcher, as reported to a [`Tracer`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    /// A match is tried from this input offset
+    Attempt { pos: usize },
+    /// The instruction at `pc` runs at input offset `pos`
+    Step { pc: usize, pos: usize },
+    /// The instruction at `pc` failed at `pos`, so the matcher backtracks
+    Fail { pc: usize, pos: usize },
+    /// The matcher resumes at `pc` the alternative that the split at `split`
+    /// left for later
+    Backtrack { split: usize, pc: usize, pos: usize },
+    /// A match was found
+    Match { start: usize, end: usize },
+}
+
+/// The tracer of normal searches, which the compiler optimizes away
+struct NoTrace;
+
+impl Tracer for NoTrace {
+    #[inline(always)]
+    fn event(&mut self, _: Event) -> bool {
+        true
+    }
+}
+
+/// Searches `haystack` for the leftmost match starting at or after `start`
+///
+/// A `start` past the end of `haystack` finds nothing.
+pub fn find_at(program: &Program, haystack: &[u8], start: usize) -> Option<Slots> {
+    search(program, haystack, start, None, &mut NoTrace)
+}
+
+/// Searches for the leftmost match whose span `accept` agrees with
+///
+/// `accept` is given the start and end of every candidate match. A rejected
+/// candidate makes the matcher backtrack, so a later or shorter alternative
+/// can still be found.
+pub fn find_accepted(
+    program: &Program,
+    haystack: &[u8],
+    start: usize,
+    accept: &dyn Fn(usize, usize) -> bool,
+) -> Option<Slots> {
+    search(program, haystack, start, Some(accept), &mut NoTrace)
+}
+
+/// Searches for the leftmost match like [`find_accepted`], or [`find_at`]
+/// without `accept`, reporting every step to `tracer`
+///
+/// The search ends without a match as soon as `tracer` asks to stop.
+pub fn trace(
+    program: &Program,
+    haystack: &[u8],
+    start: usize,
+    accept: Option<&dyn Fn(usize, usize) -> bool>,
+    tracer: &mut dyn Tracer,
+) -> Option<Slots> {
+    search(program, haystack, start, accept, tracer)
+}
+
+/// Tries every start position from `start` until a match is found
+///
+/// Without `accept`, whether a state fails doesn't depend on where the match
+/// started, so the states tried are remembered across start positions.
+fn search<T: Tracer + ?Sized>(
+    program: &Program,
+    haystack: &[u8],
+    start: usize,
+    accept: Option<&dyn Fn(usize, usize) -> bool>,
+    tracer: &mut T,
+) -> Option<Slots> {
+    if start > haystack.len() {
+        return None;
+    }
+
+    // Share the buffers between all starting positions
+    let mut slots = vec![None; program.slots];
+    let mut stack = vec![];
+    let mut memo = Memo::new(program, haystack, start);
+    let mut pos = start;
+
+    while pos <= haystack.len() {
+        // Skip ahead to the next byte a match can start with
+        if let Some(first_bytes) = &program.first_bytes {
+            pos += haystack[pos..]
+                .iter()
+                .position(|&b| first_bytes[b as usize])?;
+        }
+
+        // Matches never start in the middle of a UTF-8 encoded character
+        if program.config.unicode && haystack.get(pos).is_some_and(|&b| b & 0xC0 == 0x80) {
+            pos += 1;
+            continue;
+        }
+
+        if !tracer.event(Event::Attempt { pos }) {
+            return None;
+        }
+        if accept.is_some() {
+            memo.clear();
+        }
+        match match_pattern(
+            program, haystack, pos, accept, &mut slots, &mut stack, &mut memo, tracer,
+        ) {
+            Some(true) => return Some(slots),
+            Some(false) => {}
+            None => return None,
+        }
+
+        pos += 1;
+    }
+
+    None
+}
+
+/// The main regex matching function
+///
+/// Runs the program as a backtracking matcher anchored at `start`, trying the
+/// alternatives of every split in priority order. Returns whether a path
+/// reached the end of the program with a span that `accept` agrees with,
+/// leaving its captures in `slots`, or `None` if the tracer stopped it.
+///
+/// The buffers are reused between calls to avoid an allocation per starting
+/// position.
+#[allow(clippy::too_many_arguments)]
+fn match_pattern<T: Tracer + ?Sized>(
+    program: &Program,
+    haystack: &[u8],
+    start: usize,
+    accept: Option<&dyn Fn(usize, usize) -> bool>,
+    slots: &mut Slots,
+    stack: &mut Vec<Job>,
+    memo: &mut Memo,
+    tracer: &mut T,
+) -> Option<bool> {
+    let terminator = program.config.line_terminator;
+
+    slots.fill(None);
+    stack.clear();
+    stack.push(Job::Step {
+        pc: 0,
+        pos: start,
+        split: 0,
+    });
+
+    // Every path after the first one is taken by backtracking
+    let mut first = true;
+    while let Some(job) = stack.pop() {
+        let (mut pc, mut pos) = match job {
+            Job::Step { pc, pos, split } => {
+                if !std::mem::take(&mut first) && !tracer.event(Event::Backtrack { split, pc, pos })
+                {
+                    return None;
+                }
+                (pc, pos)
+            }
+            Job::Restore { slot, value } => {
+                slots[slot] = value;
+                continue;
+            }
+        };
+
+        // Follow this path until it fails or the pattern matches
+        loop {
+            if !tracer.event(Event::Step { pc, pos }) {
+                return None;
+            }
+
+            match &program.insts[pc] {
+                Inst::Literal(c) => {
+                    let mut buf = [0; 4];
+                    let encoded = c.encode_utf8(&mut buf).as_bytes();
+                    if !haystack[pos..].starts_with(encoded) || terminator == Some(encoded[0]) {
+                        break;
+                    }
+                    pos += encoded.len();
+                }
+                Inst::Any => match next_char(program, haystack, pos) {
+                    Some((_, len)) => pos += len,
+                    None => break,
+                },
+                Inst::Class(class) => match next_char(program, haystack, pos) {
+                    Some((c, len)) if class.contains(c) => pos += len,
+                    _ => break,
+                },
+                Inst::Start => {
+                    if pos != 0 && terminator != Some(haystack[pos - 1]) {
+                        break;
+                    }
+                }
+                Inst::End => {
+                    if !at_end(program, haystack, pos, false) {
+                        break;
+                    }
+                }
+                Inst::LineStart => {
+                    if pos != 0
+                        && haystack[pos - 1] != b'\n'
+                        && terminator != Some(haystack[pos - 1])
+                    {
+                        break;
+                    }
+                }
+                Inst::LineEnd => {
+                    if !at_end(program, haystack, pos, true) {
+                        break;
+                    }
+                }
+                Inst::EndBeforeNewline => {
+                    let rest = &haystack[pos..];
+                    let before_final_newline =
+                        rest == b"\n" || (program.config.crlf && rest == b"\r\n");
+                    if !at_end(program, haystack, pos, false) && !before_final_newline {
+                        break;
+                    }
+                }
+                Inst::WordBoundary { negated, ascii } => {
+                    let before =
+                        prev_char(program, haystack, pos).is_some_and(|c| is_word(c, *ascii));
+                    let after =
+                        next_char(program, haystack, pos).is_some_and(|(c, _)| is_word(c, *ascii));
+                    if (before != after) == *negated {
+                        break;
+                    }
+                }
+                Inst::Split(first, second) => {
+                    // Paths only diverge at splits, so remembering those is
+                    // enough. One that was tried before failed then, and
+                    // fails again.
+                    if let Some(index) = &program.memo[pc] {
+                        let fresh = index
+                            .guards
+                            .iter()
+                            .filter(|&&slot| slots[slot] == Some(pos))
+                            .count();
+                        if !memo.visit(pos, index.bit + fresh) {
+                            break;
+                        }
+                    }
+                    stack.push(Job::Step {
+                        pc: *second,
+                        pos,
+                        split: pc,
+                    });
+                    pc = *first;
+                    continue;
+                }
+                Inst::Jump(target) => {
+                    pc = *target;
+                    continue;
+                }
+                Inst::Save(slot) => {
+                    stack.push(Job::Restore {
+                        slot: *slot,
+                        value: slots[*slot],
+                    });
+                    slots[*slot] = Some(pos);
+                }
+                Inst::Progress(slot) => {
+                    if slots[*slot] == Some(pos) {
+                        break;
+                    }
+                }
+                Inst::Backreference(index) => {
+                    // A group that didn't participate can't be referenced
+                    let (Some(group_start), Some(group_end)) =
+                        (slots[index * 2], slots[index * 2 + 1])
+                    else {
+                        break;
+                    };
+
+                    let captured = &haystack[group_start..group_end];
+                    if !haystack[pos..].starts_with(captured) {
+                        break;
+                    }
+                    pos += captured.len();
+                }
+                Inst::Match => {
+                    let start = slots[0].unwrap_or(start);
+                    if accept.map_or(true, |accept| accept(start, pos)) {
+                        tracer.event(Event::Match { start, end: pos });
+                        return Some(true);
+                    }
+                    break;
+                }
+            }
+
+            pc += 1;
+        }
+
+        // Every `break` above leaves `pc` at the instruction that failed
+        if !tracer.event(Event::Fail { pc, pos }) {
+            return None;
+        }
+    }
+
+    Some(false)
+}
+
+/// Checks if the span `start..end` isn't next to a word character on
+/// either side, as `grep -w` requires
+pub fn is_whole_word(program: &Program, haystack: &[u8], start: usize, end: usize) -> bool {
+    let ascii = program.config.ascii;
+    !prev_char(program, haystack, start).is_some_and(|c| is_word(c, ascii))
+        && !next_char(program, haystack, end).is_some_and(|(c, _)| is_word(c, ascii))
+}
+
+/// Checks if `pos` is at the end of the input or right before a line
+/// terminator, or before any `\n` when `newline` is set
+///
+/// In CRLF mode, a `\r` right before such an end is part of the line ending.
+fn at_end(program: &Program, haystack: &[u8], pos: usize, newline: bool) -> bool {
+    let is_end = |pos: usize| {
+        pos == haystack.len()
+            || program.config.line_terminator == Some(haystack[pos])
+            || (newline && haystack[pos] == b'\n')
+    };
+
+    is_end(pos) || (program.config.crlf && haystack[pos] == b'\r' && is_end(pos + 1))
+}
+
+/// Decodes the character starting at `pos`
+///
+/// In Unicode mode an invalid UTF-8 sequence yields `None`, so it never
+/// matches `.` or a class. In byte mode every byte is its own character. The
+/// line terminator never matches anything.
+fn next_char(program: &Program, haystack: &[u8], pos: usize) -> Option<(char, usize)> {
+    let bytes = haystack.get(pos..)?;
+    if program.config.line_terminator == bytes.first().copied() {
+        return None;
+    }
+
+    if program.config.unicode {
+        decode_utf8(bytes)
+    } else {
+        bytes.first().map(|&b| (b as char, 1))
+    }
+}
+
+/// Decodes the character ending right before `pos`
+fn prev_char(program: &Program, haystack: &[u8], pos: usize) -> Option<char> {
+    if !program.config.unicode {
+        return pos.checked_sub(1).map(|i| haystack[i] as char);
+    }
+
+    // Walk back over at most three continuation bytes to the leading byte
+    let start = (pos.saturating_sub(4)..pos)
+        .rev()
+        .find(|&i| haystack[i] & 0xC0 != 0x80)?;
+
+    match decode_utf8(&haystack[start..pos]) {
+        Some((c, len)) if start + len == pos => Some(c),
+        _ => None,
+    }
+}
+
+/// Decodes a single UTF-8 encoded character from the front of `bytes`
+pub fn decode_utf8(bytes: &[u8]) -> Option<(char, usize)> {
+    let len = match *bytes.first()? {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return None,
+    };
+
+    let decoded = std::str::from_utf8(bytes.get(..len)?).ok()?;
+    decoded.chars().next().map(|c| (c, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Regex;
+
+    #[test]
+    fn finds_nothing_past_the_end() {
+        let regex = Regex::new("a*").unwrap();
+        let program = regex.program();
+        assert_eq!(find_at(program, b"aa", 2), Some(vec![Some(2), Some(2)]));
+        assert_eq!(find_at(program, b"aa", 3), None);
+        assert_eq!(find_at(program, b"aa", usize::MAX), None);
+        assert_eq!(find_accepted(program, b"", 5, &|_, _| true), None);
+    }
+}
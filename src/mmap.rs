@@ -0,0 +1,261 @@
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+
+use memmap2::Mmap;
+
+use crate::searcher::{Flow, Searcher, State};
+
+/// Bytes of the mapping searched between two checks of the file size
+const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Searches a file through a memory map, falling back to buffered reads
+///
+/// Only non-empty regular files are mapped; pipes and special files are read
+/// normally. Touching a page of the mapping past the end of a file that shrank
+/// raises SIGBUS, so the file size is checked again before every chunk, and
+/// the rest of the file is read through the buffered path once it got smaller.
+/// A file truncated while a chunk is being scanned makes the search fail with
+/// an error instead, on Linux. Elsewhere, it still ends the process.
+pub fn search_file<W: Write>(
+    searcher: &mut Searcher,
+    file: &File,
+    name: &str,
+    out: &mut W,
+) -> io::Result<State> {
+    let mut state = State::default();
+
+    let metadata = file.metadata()?;
+    if !metadata.is_file() || metadata.len() == 0 {
+        searcher.search_reader(file, name, out, &mut state)?;
+        return Ok(state);
+    }
+
+    // Safety: the mapping is only read while the file still covers all of it
+    let map = match unsafe { Mmap::map(file) } {
+        Ok(map) => map,
+        Err(_) => {
+            searcher.search_reader(file, name, out, &mut state)?;
+            return Ok(state);
+        }
+    };
+
+    let mut offset = 0;
+    while offset < map.len() {
+        // The file shrank: stop touching the mapping and read the rest instead
+        if file.metadata()?.len() < map.len() as u64 {
+            let mut file = file;
+            file.seek(SeekFrom::Start(offset as u64))?;
+            searcher.search_reader(file, name, out, &mut state)?;
+            return Ok(state);
+        }
+
+        let (end, flow) = guarded(&map, || {
+            // End the chunk on a line boundary, extending it for very long
+            // lines. Matches can span lines with -U, so the whole file is one
+            // chunk.
+            let terminator = searcher.terminator();
+            let limit = match searcher.multiline() {
+                true => map.len(),
+                false => (offset + CHUNK_SIZE).min(map.len()),
+            };
+            let end = match map[offset..limit].iter().rposition(|&b| b == terminator) {
+                _ if limit == map.len() => limit,
+                Some(i) => offset + i + 1,
+                None => map[limit..]
+                    .iter()
+                    .position(|&b| b == terminator)
+                    .map_or(map.len(), |i| limit + i + 1),
+            };
+
+            let flow = searcher.search_block(&map[offset..end], name, out, &mut state);
+            (end, flow)
+        })?;
+        if flow? == Flow::Stop {
+            break;
+        }
+
+        offset = end;
+    }
+
+    Ok(state)
+}
+
+/// Runs `scan`, which reads from `map`, turning the SIGBUS raised by a page
+/// the file no longer covers into an error
+///
+/// The page that faulted reads as zeros, so `scan` still finishes, but what
+/// it found there is wrong.
+#[cfg(target_os = "linux")]
+fn guarded<T>(map: &[u8], scan: impl FnOnce() -> T) -> io::Result<T> {
+    sigbus::install();
+    sigbus::GUARDED.with(|guarded| guarded.set((map.as_ptr() as usize, map.len())));
+    let result = scan();
+    sigbus::GUARDED.with(|guarded| guarded.set((0, 0)));
+
+    if sigbus::FAULTED.with(|faulted| faulted.replace(false)) {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "file shrank while it was searched",
+        ));
+    }
+    Ok(result)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn guarded<T>(_: &[u8], scan: impl FnOnce() -> T) -> io::Result<T> {
+    Ok(scan())
+}
+
+#[cfg(target_os = "linux")]
+mod sigbus {
+    use std::cell::Cell;
+    use std::mem;
+    use std::ptr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Once, OnceLock};
+
+    thread_local! {
+        /// Address and length of the mapping this thread is reading
+        pub static GUARDED: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+        /// Whether a page of that mapping faulted
+        pub static FAULTED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+    /// The handler before ours, which handles every other SIGBUS
+    static PREVIOUS: OnceLock<libc::sigaction> = OnceLock::new();
+
+    /// Installs the handler, once for the whole process
+    pub fn install() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| unsafe {
+            PAGE_SIZE.store(
+                libc::sysconf(libc::_SC_PAGESIZE) as usize,
+                Ordering::Relaxed,
+            );
+
+            let mut previous: libc::sigaction = mem::zeroed();
+            if libc::sigaction(libc::SIGBUS, ptr::null(), &mut previous) != 0 {
+                return;
+            }
+            PREVIOUS.get_or_init(|| previous);
+
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = on_sigbus as *const () as usize;
+            action.sa_flags = libc::SA_SIGINFO;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(libc::SIGBUS, &action, ptr::null_mut());
+        });
+    }
+
+    /// Maps a page of zeros over a faulting page of the guarded mapping
+    ///
+    /// Returning from the handler runs the faulting read again. For a fault
+    /// anywhere else, the previous handler is put back first, so it handles
+    /// that read instead.
+    extern "C" fn on_sigbus(_: libc::c_int, info: *mut libc::siginfo_t, _: *mut libc::c_void) {
+        let addr = unsafe { (*info).si_addr() } as usize;
+        let (start, len) = GUARDED.with(Cell::get);
+        if addr.wrapping_sub(start) < len {
+            let page_size = PAGE_SIZE.load(Ordering::Relaxed);
+            let page = addr & !(page_size - 1);
+            let zeros = unsafe {
+                libc::mmap(
+                    page as *mut libc::c_void,
+                    page_size,
+                    libc::PROT_READ,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_FIXED,
+                    -1,
+                    0,
+                )
+            };
+            if zeros != libc::MAP_FAILED {
+                FAULTED.with(|faulted| faulted.set(true));
+                return;
+            }
+        }
+
+        if let Some(previous) = PREVIOUS.get() {
+            unsafe { libc::sigaction(libc::SIGBUS, previous, ptr::null_mut()) };
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::fs::{self, OpenOptions};
+
+    use codecrafters_grep::{Config, Regex};
+
+    use super::*;
+    use crate::searcher::SearchOptions;
+
+    #[test]
+    fn reports_a_file_truncated_under_the_map() {
+        let path = std::env::temp_dir().join(format!("grep-mmap-{}", std::process::id()));
+        fs::write(&path, vec![b'a'; 64 * 1024]).unwrap();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap();
+        let map = unsafe { Mmap::map(&file) }.unwrap();
+
+        let sum = || map.iter().map(|&b| b as usize).sum::<usize>();
+        assert_eq!(guarded(&map, sum).unwrap(), 64 * 1024 * b'a' as usize);
+
+        file.set_len(100).unwrap();
+        let err = guarded(&map, sum).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        // Later reads see the zeros without faulting
+        assert!(guarded(&map, sum).is_ok());
+
+        drop(map);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn finds_what_buffered_reads_find() {
+        let path = std::env::temp_dir().join(format!("grep-mmap-lines-{}", std::process::id()));
+        // Lines of 15 bytes, so the first chunk ends within line 559240
+        let text: String = (0..(CHUNK_SIZE / 15 + 1000))
+            .map(|i| format!("line {:>9}\n", i))
+            .collect();
+        fs::write(&path, &text).unwrap();
+
+        let config = Config {
+            line_terminator: Some(b'\n'),
+            ..Config::default()
+        };
+        let regex = Regex::with_config("9999[09]$|^line  *0$|559240$", config).unwrap();
+        let mut searcher = Searcher::new(regex.program(), SearchOptions::default());
+
+        let file = File::open(&path).unwrap();
+        let mut mapped = vec![];
+        search_file(&mut searcher, &file, "f", &mut mapped).unwrap();
+        let mut read = vec![];
+        searcher
+            .search_reader(text.as_bytes(), "f", &mut read, &mut State::default())
+            .unwrap();
+
+        assert_eq!(mapped, read);
+        assert_eq!(
+            String::from_utf8(mapped).unwrap(),
+            "line         0\n\
+             line     99990\n\
+             line     99999\n\
+             line    199990\n\
+             line    199999\n\
+             line    299990\n\
+             line    299999\n\
+             line    399990\n\
+             line    399999\n\
+             line    499990\n\
+             line    499999\n\
+             line    559240\n"
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::searcher::Flow;
+
+/// Runs `work` for every index in `0..count` on a pool of `jobs` threads
+///
+/// Results are handed to `emit` on the calling thread, one at a time, so the
+/// output of one job is never interleaved with another's. With `ordered` set
+/// they're emitted in index order, holding back results that finish early;
+/// otherwise each result is emitted as soon as it's ready.
+///
+/// When `emit` returns `Flow::Stop`, no further indices are started and the
+/// remaining results are dropped. Jobs already running still finish, so
+/// `work` should check for itself when it's no longer needed.
+pub fn for_each<T, W, E>(count: usize, jobs: usize, ordered: bool, work: W, mut emit: E)
+where
+    T: Send,
+    W: Fn(usize) -> T + Sync,
+    E: FnMut(T) -> Flow,
+{
+    let next = AtomicUsize::new(0);
+    let stopped = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, count.max(1)) {
+            let sender = sender.clone();
+            let (next, stopped, work) = (&next, &stopped, &work);
+
+            // Each worker keeps taking the next unclaimed index
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= count || stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+                if sender.send((index, work(index))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut expected = 0;
+
+        'receive: for (index, result) in receiver.iter() {
+            if !ordered {
+                if emit(result) == Flow::Stop {
+                    break;
+                }
+                continue;
+            }
+
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&expected) {
+                if emit(result) == Flow::Stop {
+                    break 'receive;
+                }
+                expected += 1;
+            }
+        }
+
+        // Workers stop claiming indices, and their next send fails
+        stopped.store(true, Ordering::Relaxed);
+        drop(receiver);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Runs jobs where the first ones finish last, collecting what's emitted
+    fn run(count: usize, ordered: bool, stop_at: Option<usize>) -> Vec<usize> {
+        let mut emitted = vec![];
+        for_each(
+            count,
+            4,
+            ordered,
+            |index| {
+                thread::sleep(Duration::from_millis(((count - index) * 5) as u64));
+                index
+            },
+            |index| {
+                emitted.push(index);
+                match stop_at == Some(index) {
+                    true => Flow::Stop,
+                    false => Flow::Continue,
+                }
+            },
+        );
+        emitted
+    }
+
+    #[test]
+    fn emits_in_index_order_when_ordered() {
+        assert_eq!(run(12, true, None), (0..12).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn emits_everything_when_unordered() {
+        let mut emitted = run(12, false, None);
+        emitted.sort();
+        assert_eq!(emitted, (0..12).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stops_emitting_when_asked() {
+        assert_eq!(run(12, true, Some(2)), [0, 1, 2]);
+        assert_eq!(run(0, true, None), []);
+    }
+}
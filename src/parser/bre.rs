@@ -0,0 +1,47 @@
+use super::{Parser, Result, Token};
+use crate::error::PatternError;
+
+/// Reads the next token of a basic regular expression
+///
+/// Apart from `*`, `.` and `[`, operators are spelled with a backslash and
+/// the bare characters are literals. Like GNU grep, `^` is only an anchor at
+/// the start of a branch and `$` only at its end.
+pub(super) fn token(parser: &mut Parser, branch_start: bool) -> Option<Result<Token>> {
+    let offset = parser.pos;
+
+    Some(Ok(match parser.next()? {
+        '*' => Token::Quantifier {
+            min: 0,
+            max: None,
+            symbol: '*',
+        },
+        '.' => Token::Any,
+        '[' => Token::ClassOpen,
+        '^' if branch_start => Token::Start,
+        '$' if at_branch_end(parser) => Token::End,
+        '\\' => match parser.next() {
+            Some('(') => Token::GroupOpen,
+            Some(')') => Token::GroupClose,
+            Some('|') => Token::Alternate,
+            Some('{') => Token::IntervalOpen,
+            Some('+') => Token::Quantifier {
+                min: 1,
+                max: None,
+                symbol: '+',
+            },
+            Some('?') => Token::Quantifier {
+                min: 0,
+                max: Some(1),
+                symbol: '?',
+            },
+            Some(c) => Token::Escape(c),
+            None => return Some(Err(PatternError::BadEscape { offset })),
+        },
+        x => Token::Literal(x),
+    }))
+}
+
+/// Checks if the parser is at the end of the pattern, a group or a branch
+fn at_branch_end(parser: &Parser) -> bool {
+    parser.peek().is_none() || parser.lookahead(&['\\', ')']) || parser.lookahead(&['\\', '|'])
+}
@@ -0,0 +1,40 @@
+use super::{Parser, Result, Token};
+use crate::error::PatternError;
+
+/// Reads the next token of an extended regular expression
+///
+/// Operators are bare characters; a backslash makes them literal.
+pub(super) fn token(parser: &mut Parser) -> Option<Result<Token>> {
+    let offset = parser.pos;
+
+    Some(Ok(match parser.next()? {
+        '(' => Token::GroupOpen,
+        ')' => Token::GroupClose,
+        '|' => Token::Alternate,
+        '*' => Token::Quantifier {
+            min: 0,
+            max: None,
+            symbol: '*',
+        },
+        '+' => Token::Quantifier {
+            min: 1,
+            max: None,
+            symbol: '+',
+        },
+        '?' => Token::Quantifier {
+            min: 0,
+            max: Some(1),
+            symbol: '?',
+        },
+        '{' => Token::IntervalOpen,
+        '^' => Token::Start,
+        '$' => Token::End,
+        '.' => Token::Any,
+        '[' => Token::ClassOpen,
+        '\\' => match parser.next() {
+            Some(c) => Token::Escape(c),
+            None => return Some(Err(PatternError::BadEscape { offset })),
+        },
+        x => Token::Literal(x),
+    }))
+}
@@ -0,0 +1,730 @@
+mod bre;
+mod ere;
+mod pcre;
+
+use crate::ast::{Class, ClassItem, Pattern, PosixClass};
+use crate::error::PatternError;
+use crate::program::Config;
+use crate::unicode::Property;
+
+type Result<T> = std::result::Result<T, PatternError>;
+
+/// Largest count an interval like `{n,m}` may use, POSIX's `RE_DUP_MAX`
+const MAX_REPEAT: u32 = 32767;
+
+/// The regular expression dialects understood by the parser
+///
+/// Each dialect is a front-end that only decides how operators are spelled;
+/// they all produce the same pattern tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Syntax {
+    /// POSIX basic regular expressions (`grep -G`), where `\(`, `\)`, `\{`,
+    /// `\}`, `\|`, `\+` and `\?` are the operators
+    Basic,
+    /// POSIX extended regular expressions (`grep -E`)
+    #[default]
+    Extended,
+    /// Perl-compatible regular expressions (`grep -P`), with inline flags,
+    /// `\Q...\E` quoting and the `\Z` and `\K` escapes
+    Perl,
+}
+
+/// Parses a pattern into a pattern tree, using the dialect and the initial
+/// flags of `config`
+pub fn parse(pattern: &str, config: &Config) -> Result<Pattern> {
+    let mut parser = Parser {
+        chars: pattern.chars().collect(),
+        pos: 0,
+        groups: 0,
+        names: vec![],
+        depth: 0,
+        syntax: config.syntax,
+        flags: Flags {
+            multi_line: config.multi_line,
+            ascii: config.ascii,
+            ..Flags::default()
+        },
+        quoting: false,
+    };
+
+    parser.parse_alternation()
+}
+
+/// `.` when it mustn't match a newline, as a class of everything else
+fn any_but_newline() -> Pattern {
+    Pattern::Class(Class {
+        negated: true,
+        items: vec![ClassItem::Char('\n')],
+        case_insensitive: false,
+        ascii: false,
+    })
+}
+
+/// Flags that change how atoms are parsed, like the `i` in `(?i)`
+///
+/// Only Perl patterns can change them inline.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Flags {
+    case_insensitive: bool,
+    multi_line: bool,
+    dot_all: bool,
+    /// Whitespace and `#` comments between tokens are ignored
+    extended: bool,
+    /// Shorthand classes, word boundaries and case folding are ASCII-only
+    ascii: bool,
+}
+
+/// A pattern element, independent of how a dialect spells it
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    GroupOpen,
+    /// `(?:...)`, with the flags in effect inside the group
+    NonCapturing(Flags),
+    /// `(?<name>...)`, a capture group that can also be referred to by name
+    NamedGroup(String),
+    /// `(?i)` and the like, with the flags for the rest of the group
+    SetFlags(Flags),
+    GroupClose,
+    Alternate,
+    /// `*`, `+` or `?`, with the character that spelled it
+    Quantifier {
+        min: u32,
+        max: Option<u32>,
+        symbol: char,
+    },
+    /// The start of a `{n,m}` interval
+    IntervalOpen,
+    Start,
+    End,
+    Any,
+    ClassOpen,
+    /// A backslash followed by a character the dialect gives no meaning to
+    Escape(char),
+    Literal(char),
+    /// An atom the dialect resolved itself, like `\A`
+    Atom(Pattern),
+}
+
+/// A recursive descent parser over the characters of a pattern
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    groups: usize,
+    /// Names given to groups so far, which must be unique
+    names: Vec<String>,
+    /// Number of groups currently open, so a stray ')' can be taken literally
+    depth: usize,
+    syntax: Syntax,
+    flags: Flags,
+    /// Whether the parser is between a `\Q` and its `\E`
+    quoting: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    /// Checks if the rest of the pattern starts with `prefix`
+    fn lookahead(&self, prefix: &[char]) -> bool {
+        self.chars[self.pos.min(self.chars.len())..].starts_with(prefix)
+    }
+
+    /// Reads the next token, using the rules of the parser's dialect
+    ///
+    /// `branch_start` tells the dialect whether nothing has been parsed yet in
+    /// the current branch, which changes the meaning of `^` and `*` in BREs.
+    fn token(&mut self, branch_start: bool) -> Option<Result<Token>> {
+        self.skip_trivia();
+        match self.syntax {
+            Syntax::Basic => bre::token(self, branch_start),
+            Syntax::Extended => ere::token(self),
+            Syntax::Perl => pcre::token(self),
+        }
+    }
+
+    /// Skips what the dialect ignores between tokens, and returns the offset
+    /// of the next token
+    ///
+    /// Positions saved for backtracking must come from here, so that the
+    /// `\Q` and `\E` markers aren't read twice.
+    fn skip_trivia(&mut self) -> usize {
+        if self.syntax == Syntax::Perl {
+            pcre::skip_trivia(self);
+        }
+        self.pos
+    }
+
+    /// alternation := concat ('|' concat)*
+    fn parse_alternation(&mut self) -> Result<Pattern> {
+        let mut branches = vec![self.parse_concat()?];
+
+        loop {
+            let offset = self.skip_trivia();
+            if self.token(false).transpose()? != Some(Token::Alternate) {
+                self.pos = offset;
+                break;
+            }
+
+            branches.push(self.parse_concat()?);
+        }
+
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Pattern::Alternation(branches)
+        })
+    }
+
+    /// concat := repeat*
+    ///
+    /// Stops right before the '|' or ')' that ends the branch.
+    fn parse_concat(&mut self) -> Result<Pattern> {
+        let mut items = vec![];
+
+        loop {
+            let offset = self.skip_trivia();
+            let Some(token) = self.token(items.is_empty()).transpose()? else {
+                break;
+            };
+
+            if token == Token::Alternate || (token == Token::GroupClose && self.depth > 0) {
+                self.pos = offset;
+                break;
+            }
+
+            // Inline flags apply to the rest of the enclosing group
+            if let Token::SetFlags(flags) = token {
+                self.flags = flags;
+                continue;
+            }
+
+            let atom = self.parse_atom(token, offset)?;
+            items.push(self.parse_quantifiers(atom)?);
+        }
+
+        Ok(match items.len() {
+            0 => Pattern::Empty,
+            1 => items.pop().unwrap(),
+            _ => Pattern::Concat(items),
+        })
+    }
+
+    /// Wraps an atom in any quantifiers that follow it
+    fn parse_quantifiers(&mut self, mut atom: Pattern) -> Result<Pattern> {
+        // In a BRE, a '*' right after a leading '^' is a literal
+        if self.syntax == Syntax::Basic && atom == Pattern::Start {
+            return Ok(atom);
+        }
+
+        loop {
+            let offset = self.skip_trivia();
+            let (min, max) = match self.token(false).transpose()? {
+                Some(Token::IntervalOpen) => match self.parse_interval(offset)? {
+                    Some(bounds) => bounds,
+                    None => {
+                        self.pos = offset;
+                        return Ok(atom);
+                    }
+                },
+                Some(Token::Quantifier { min, max, .. }) => (min, max),
+                _ => {
+                    self.pos = offset;
+                    return Ok(atom);
+                }
+            };
+
+            // A trailing '?' makes a Perl quantifier lazy
+            let lazy = self.syntax == Syntax::Perl && self.peek() == Some('?');
+            if lazy {
+                self.pos += 1;
+            }
+
+            atom = Pattern::Repeat {
+                min,
+                max,
+                greedy: !lazy,
+                pattern: Box::new(atom),
+            };
+        }
+    }
+
+    /// Parses the `n}`, `n,}` or `n,m}` after the opening of an interval
+    ///
+    /// In an ERE, a `{` that doesn't start a valid interval is treated as a
+    /// literal, like GNU grep does, and `None` is returned.
+    fn parse_interval(&mut self, start: usize) -> Result<Option<(u32, Option<u32>)>> {
+        let min = self.parse_number();
+        let max = if self.peek() == Some(',') {
+            self.pos += 1;
+            self.parse_number()
+        } else {
+            min
+        };
+
+        let close: &[char] = match self.syntax {
+            Syntax::Basic => &['\\', '}'],
+            Syntax::Extended | Syntax::Perl => &['}'],
+        };
+
+        if !self.lookahead(close) || (min.is_none() && max.is_none()) {
+            return match self.syntax {
+                Syntax::Basic => Err(PatternError::BadInterval { offset: start }),
+                Syntax::Extended | Syntax::Perl => Ok(None),
+            };
+        }
+
+        self.pos += close.len();
+
+        let min = min.unwrap_or(0);
+        if min.max(max.unwrap_or(0)) > MAX_REPEAT {
+            return Err(PatternError::RepeatTooLarge { offset: start });
+        }
+        if let Some(max) = max {
+            if max < min {
+                return Err(PatternError::InvalidRange { offset: start });
+            }
+        }
+
+        Ok(Some((min, max)))
+    }
+
+    /// Reads a decimal number, saturating at `u32::MAX`
+    fn parse_number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        match digits.is_empty() {
+            true => None,
+            false => Some(digits.parse().unwrap_or(u32::MAX)),
+        }
+    }
+
+    /// atom := group | class | escape | anchor | literal
+    fn parse_atom(&mut self, token: Token, offset: usize) -> Result<Pattern> {
+        Ok(match token {
+            Token::GroupOpen | Token::NonCapturing(_) | Token::NamedGroup(_) => {
+                let outer_flags = self.flags;
+                let (index, name) = match token {
+                    Token::NonCapturing(flags) => {
+                        self.flags = flags;
+                        (None, None)
+                    }
+                    Token::NamedGroup(name) => {
+                        if self.names.contains(&name) {
+                            return Err(PatternError::BadGroupName { offset });
+                        }
+                        self.names.push(name.clone());
+                        self.groups += 1;
+                        (Some(self.groups), Some(name))
+                    }
+                    _ => {
+                        self.groups += 1;
+                        (Some(self.groups), None)
+                    }
+                };
+
+                self.depth += 1;
+                let pattern = self.parse_alternation()?;
+                self.depth -= 1;
+
+                if self.token(false).transpose()? != Some(Token::GroupClose) {
+                    return Err(PatternError::UnclosedGroup { offset });
+                }
+                self.flags = outer_flags;
+
+                match index {
+                    Some(index) => Pattern::Group {
+                        index,
+                        name,
+                        pattern: Box::new(pattern),
+                    },
+                    None => pattern,
+                }
+            }
+            // Only reached outside of any group
+            Token::GroupClose => Pattern::Literal(')'),
+            Token::ClassOpen => {
+                let class = self.parse_class(offset)?;
+                self.class(class)
+            }
+            Token::Escape(c) => self.parse_escape(c, offset)?,
+            // Matches span lines with -U, but `.` still stops at a newline
+            Token::Any if self.flags.multi_line => any_but_newline(),
+            Token::Any => Pattern::Wildcard,
+            Token::Start if self.flags.multi_line => Pattern::LineStart,
+            Token::Start => Pattern::Start,
+            Token::End if self.flags.multi_line => Pattern::LineEnd,
+            Token::End => Pattern::End,
+            Token::Literal(x) if self.flags.case_insensitive => self.class(Class::caseless(x)),
+            Token::Literal(x) => Pattern::Literal(x),
+            Token::Atom(atom) => atom,
+            // A BRE takes a quantifier with nothing before it literally, and
+            // an ERE ignores it, like GNU grep does
+            Token::Quantifier { symbol, .. } if self.syntax == Syntax::Basic => {
+                Pattern::Literal(symbol)
+            }
+            Token::Quantifier { .. } if self.syntax == Syntax::Extended => Pattern::Empty,
+            Token::IntervalOpen if self.syntax != Syntax::Basic => Pattern::Literal('{'),
+            Token::Quantifier { .. } | Token::IntervalOpen => {
+                return Err(PatternError::DanglingQuantifier { offset })
+            }
+            Token::Alternate | Token::SetFlags(_) => unreachable!("handled by the branch"),
+        })
+    }
+
+    /// Parses a backslash escape found at `offset`
+    ///
+    /// Escaped punctuation stands for itself, but letters and digits without
+    /// a special meaning are rejected.
+    fn parse_escape(&mut self, c: char, offset: usize) -> Result<Pattern> {
+        Ok(match c {
+            'd' => self.class(Class::shorthand(ClassItem::Digit)),
+            'D' => self.class(Class::shorthand(ClassItem::NotDigit)),
+            'w' => self.class(Class::shorthand(ClassItem::Word)),
+            'W' => self.class(Class::shorthand(ClassItem::NotWord)),
+            's' => self.class(Class::shorthand(ClassItem::Space)),
+            'S' => self.class(Class::shorthand(ClassItem::NotSpace)),
+            'p' | 'P' => {
+                let property = self.parse_property(c, offset)?;
+                self.class(Class::shorthand(property))
+            }
+            'A' => Pattern::Start,
+            'z' => Pattern::End,
+            'b' | 'B' => Pattern::WordBoundary {
+                negated: c == 'B',
+                ascii: self.flags.ascii,
+            },
+            '1'..='9' => {
+                let index = c.to_digit(10).unwrap() as usize;
+                if index > self.groups {
+                    return Err(PatternError::BadBackreference { offset });
+                }
+
+                Pattern::Backreference(index)
+            }
+            x if x.is_alphanumeric() => return Err(PatternError::BadEscape { offset }),
+            x => Pattern::Literal(x),
+        })
+    }
+
+    /// Turns a class into an atom that follows the flags in effect
+    fn class(&self, mut class: Class) -> Pattern {
+        class.case_insensitive |= self.flags.case_insensitive;
+        class.ascii = self.flags.ascii;
+        Pattern::Class(class)
+    }
+
+    /// Parses the name after `\p` or `\P`, either a single letter or braced
+    ///
+    /// `\P{..}` and `\p{^..}` are negated, and both together cancel out.
+    fn parse_property(&mut self, escape: char, offset: usize) -> Result<ClassItem> {
+        let mut negated = escape == 'P';
+
+        let name: String = if self.peek() == Some('{') {
+            let start = self.pos + 1;
+            let Some(len) = self.chars[start..].iter().position(|&c| c == '}') else {
+                return Err(PatternError::UnknownProperty { offset });
+            };
+            self.pos = start + len + 1;
+
+            let mut name = &self.chars[start..start + len];
+            if name.first() == Some(&'^') {
+                negated = !negated;
+                name = &name[1..];
+            }
+            name.iter().collect()
+        } else {
+            match self.next() {
+                Some(c) => c.to_string(),
+                None => return Err(PatternError::UnknownProperty { offset }),
+            }
+        };
+
+        let property = Property::lookup(&name).ok_or(PatternError::UnknownProperty { offset })?;
+        Ok(if negated {
+            ClassItem::NotProperty(property)
+        } else {
+            ClassItem::Property(property)
+        })
+    }
+
+    /// Parses a bracket expression opened at `offset`, with the '[' already consumed
+    fn parse_class(&mut self, offset: usize) -> Result<Class> {
+        let mut items = vec![];
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+
+        // A ']' right after the opening bracket is a literal member
+        let mut first = true;
+
+        loop {
+            let item_offset = self.pos;
+            let item = match self.next() {
+                None => return Err(PatternError::UnclosedClass { offset }),
+                Some(']') if !first => break,
+                Some('[') if self.peek() == Some(':') => match self.parse_posix_class()? {
+                    Some(class) => ClassItem::Posix(class),
+                    None => ClassItem::Char('['),
+                },
+                // POSIX brackets take a backslash literally
+                Some('\\') if self.syntax != Syntax::Perl => ClassItem::Char('\\'),
+                Some('\\') => match self.next() {
+                    Some('d') => ClassItem::Digit,
+                    Some('D') => ClassItem::NotDigit,
+                    Some('w') => ClassItem::Word,
+                    Some('W') => ClassItem::NotWord,
+                    Some('s') => ClassItem::Space,
+                    Some('S') => ClassItem::NotSpace,
+                    Some(c @ ('p' | 'P')) => self.parse_property(c, item_offset)?,
+                    Some('n') if self.syntax == Syntax::Perl => ClassItem::Char('\n'),
+                    Some('r') if self.syntax == Syntax::Perl => ClassItem::Char('\r'),
+                    Some('t') if self.syntax == Syntax::Perl => ClassItem::Char('\t'),
+                    Some(x) if self.syntax == Syntax::Perl && x.is_alphanumeric() => {
+                        return Err(PatternError::BadEscape {
+                            offset: item_offset,
+                        })
+                    }
+                    Some(x) => ClassItem::Char(x),
+                    None => return Err(PatternError::UnclosedClass { offset }),
+                },
+                Some(x) => ClassItem::Char(x),
+            };
+            first = false;
+
+            // Turn `a-z` into a range, but keep a trailing '-' literal
+            if let ClassItem::Char(start) = item {
+                if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                    if let Some(&end) = self.chars.get(self.pos + 1) {
+                        if end < start {
+                            return Err(PatternError::InvalidRange {
+                                offset: item_offset,
+                            });
+                        }
+
+                        self.pos += 2;
+                        items.push(ClassItem::Range(start, end));
+                        continue;
+                    }
+                }
+            }
+
+            items.push(item);
+        }
+
+        Ok(Class {
+            negated,
+            items,
+            case_insensitive: false,
+            ascii: false,
+        })
+    }
+
+    /// Parses the `:name:]` of a POSIX class, after its opening '['
+    ///
+    /// Without a closing `:]`, the '[' is an ordinary member and `None` is
+    /// returned.
+    fn parse_posix_class(&mut self) -> Result<Option<PosixClass>> {
+        let offset = self.pos - 1;
+        let start = self.pos + 1;
+        let Some(len) = self.chars[start..]
+            .windows(2)
+            .position(|pair| pair == [':', ']'])
+        else {
+            return Ok(None);
+        };
+
+        let name: String = self.chars[start..start + len].iter().collect();
+        let class =
+            PosixClass::from_name(&name).ok_or(PatternError::UnknownPosixClass { offset })?;
+        self.pos = start + len + 2;
+        Ok(Some(class))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_as(syntax: Syntax, pattern: &str) -> Result<Pattern> {
+        let config = Config {
+            syntax,
+            ..Config::default()
+        };
+        parse(pattern, &config)
+    }
+
+    fn class(items: Vec<ClassItem>) -> Pattern {
+        Pattern::Class(Class {
+            negated: false,
+            items,
+            case_insensitive: false,
+            ascii: false,
+        })
+    }
+
+    #[test]
+    fn basic_syntax_escapes_operators() {
+        let group = |pattern| Pattern::Group {
+            index: 1,
+            name: None,
+            pattern: Box::new(pattern),
+        };
+        assert_eq!(
+            parse_as(Syntax::Basic, r"\(a\)"),
+            Ok(group(Pattern::Literal('a')))
+        );
+        assert_eq!(
+            parse_as(Syntax::Basic, "(a)"),
+            Ok(Pattern::Concat(vec![
+                Pattern::Literal('('),
+                Pattern::Literal('a'),
+                Pattern::Literal(')'),
+            ]))
+        );
+        assert_eq!(
+            parse_as(Syntax::Extended, "(a)"),
+            Ok(group(Pattern::Literal('a')))
+        );
+    }
+
+    #[test]
+    fn quantifiers_without_an_atom() {
+        let star_a = Pattern::Concat(vec![Pattern::Literal('*'), Pattern::Literal('a')]);
+        assert_eq!(parse_as(Syntax::Basic, "*a"), Ok(star_a.clone()));
+        assert_eq!(
+            parse_as(Syntax::Basic, r"b\|*a"),
+            Ok(Pattern::Alternation(vec![Pattern::Literal('b'), star_a]))
+        );
+
+        // GNU grep -E ignores them
+        let empty_a = Pattern::Concat(vec![Pattern::Empty, Pattern::Literal('a')]);
+        assert_eq!(parse_as(Syntax::Extended, "*a"), Ok(empty_a.clone()));
+        assert_eq!(
+            parse_as(Syntax::Extended, "b|+a"),
+            Ok(Pattern::Alternation(vec![Pattern::Literal('b'), empty_a]))
+        );
+        assert!(parse_as(Syntax::Extended, "(?a)").is_ok());
+
+        assert_eq!(
+            parse_as(Syntax::Perl, "b|*a"),
+            Err(PatternError::DanglingQuantifier { offset: 2 })
+        );
+    }
+
+    #[test]
+    fn perl_class_escapes() {
+        assert_eq!(
+            parse_as(Syntax::Perl, r"[\t\n\r]"),
+            Ok(class(vec![
+                ClassItem::Char('\t'),
+                ClassItem::Char('\n'),
+                ClassItem::Char('\r'),
+            ]))
+        );
+        assert_eq!(
+            parse_as(Syntax::Perl, r"[\d\-]"),
+            Ok(class(vec![ClassItem::Digit, ClassItem::Char('-')]))
+        );
+        assert_eq!(
+            parse_as(Syntax::Perl, r"a[\q]"),
+            Err(PatternError::BadEscape { offset: 2 })
+        );
+    }
+
+    #[test]
+    fn posix_brackets_take_backslash_literally() {
+        for syntax in [Syntax::Basic, Syntax::Extended] {
+            assert_eq!(
+                parse_as(syntax, r"[\]"),
+                Ok(class(vec![ClassItem::Char('\\')]))
+            );
+            assert_eq!(
+                parse_as(syntax, r"[\t]"),
+                Ok(class(vec![ClassItem::Char('\\'), ClassItem::Char('t')]))
+            );
+        }
+    }
+
+    #[test]
+    fn posix_class_names() {
+        assert_eq!(
+            parse_as(Syntax::Extended, "[[:alpha:]_]"),
+            Ok(class(vec![
+                ClassItem::Posix(PosixClass::Alpha),
+                ClassItem::Char('_'),
+            ]))
+        );
+        assert_eq!(
+            parse_as(Syntax::Perl, "[[:digit:][:space:]]"),
+            Ok(class(vec![
+                ClassItem::Posix(PosixClass::Digit),
+                ClassItem::Posix(PosixClass::Space),
+            ]))
+        );
+        assert_eq!(
+            parse_as(Syntax::Extended, "[[:alph:]]"),
+            Err(PatternError::UnknownPosixClass { offset: 1 })
+        );
+        // Without a closing `:]`, the '[' is a member
+        assert_eq!(
+            parse_as(Syntax::Extended, "[[:a]"),
+            Ok(class(vec![
+                ClassItem::Char('['),
+                ClassItem::Char(':'),
+                ClassItem::Char('a')
+            ]))
+        );
+    }
+
+    #[test]
+    fn dot_stops_at_newline_in_multi_line_mode() {
+        let config = Config {
+            multi_line: true,
+            ..Config::default()
+        };
+        let dot = parse(".", &config).unwrap();
+        let Pattern::Class(class) = dot else {
+            panic!("expected a class, got {:?}", dot);
+        };
+        assert!(!class.contains('\n'));
+        assert!(class.contains('x'));
+
+        assert_eq!(parse_as(Syntax::Extended, "."), Ok(Pattern::Wildcard));
+    }
+
+    #[test]
+    fn interval_bounds() {
+        let repeat = |min, max| Pattern::Repeat {
+            min,
+            max,
+            greedy: true,
+            pattern: Box::new(Pattern::Literal('a')),
+        };
+        assert_eq!(parse_as(Syntax::Extended, "a{2,5}"), Ok(repeat(2, Some(5))));
+        assert_eq!(parse_as(Syntax::Extended, "a{3,}"), Ok(repeat(3, None)));
+        assert_eq!(
+            parse_as(Syntax::Extended, "a{5,2}"),
+            Err(PatternError::InvalidRange { offset: 1 })
+        );
+        assert_eq!(
+            parse_as(Syntax::Extended, "a{1,99999999999}"),
+            Err(PatternError::RepeatTooLarge { offset: 1 })
+        );
+        assert_eq!(
+            parse_as(Syntax::Basic, r"a\{32768\}"),
+            Err(PatternError::RepeatTooLarge { offset: 1 })
+        );
+    }
+}
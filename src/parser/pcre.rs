@@ -0,0 +1,141 @@
+use super::{any_but_newline, Parser, Result, Token};
+use crate::ast::Pattern;
+use crate::error::PatternError;
+
+/// Reads the next token of a Perl-compatible regular expression
+///
+/// Operators are spelled like in an ERE. What `^`, `$` and `.` match depends
+/// on the inline flags in effect, so they're resolved here.
+pub(super) fn token(parser: &mut Parser) -> Option<Result<Token>> {
+    let offset = parser.pos;
+    let c = parser.next()?;
+
+    // Everything between `\Q` and `\E` is literal
+    if parser.quoting {
+        return Some(Ok(Token::Literal(c)));
+    }
+
+    let flags = parser.flags;
+    Some(Ok(match c {
+        '(' if parser.peek() == Some('?') => return Some(group(parser, offset)),
+        '(' => Token::GroupOpen,
+        ')' => Token::GroupClose,
+        '|' => Token::Alternate,
+        '*' => Token::Quantifier {
+            min: 0,
+            max: None,
+            symbol: '*',
+        },
+        '+' => Token::Quantifier {
+            min: 1,
+            max: None,
+            symbol: '+',
+        },
+        '?' => Token::Quantifier {
+            min: 0,
+            max: Some(1),
+            symbol: '?',
+        },
+        '{' => Token::IntervalOpen,
+        '^' if flags.multi_line => Token::Atom(Pattern::LineStart),
+        '^' => Token::Atom(Pattern::Start),
+        '$' if flags.multi_line => Token::Atom(Pattern::LineEnd),
+        '$' => Token::Atom(Pattern::EndBeforeNewline),
+        '.' if flags.dot_all => Token::Atom(Pattern::Wildcard),
+        '.' => Token::Atom(any_but_newline()),
+        '[' => Token::ClassOpen,
+        '\\' => match parser.next() {
+            Some('Z') => Token::Atom(Pattern::EndBeforeNewline),
+            Some('K') => Token::Atom(Pattern::ResetStart),
+            Some('n') => Token::Literal('\n'),
+            Some('r') => Token::Literal('\r'),
+            Some('t') => Token::Literal('\t'),
+            Some(c) => Token::Escape(c),
+            None => return Some(Err(PatternError::BadEscape { offset })),
+        },
+        x => Token::Literal(x),
+    }))
+}
+
+/// Reads the rest of a `(?` construct opened at `offset`
+///
+/// Supports non-capturing groups `(?:...)`, named groups `(?<name>...)`,
+/// inline flags like `(?i)` or `(?m-u)` and scoped flags `(?i:...)`.
+fn group(parser: &mut Parser, offset: usize) -> Result<Token> {
+    parser.pos += 1;
+
+    // `(?P<name>...)` and `(?'name'...)` are other spellings of a named group
+    if parser.lookahead(&['P', '<']) {
+        parser.pos += 1;
+    }
+    let close = match parser.peek() {
+        Some('<') => Some('>'),
+        Some('\'') => Some('\''),
+        _ => None,
+    };
+    if let Some(close) = close {
+        parser.pos += 1;
+        return group_name(parser, close, offset).map(Token::NamedGroup);
+    }
+
+    let mut flags = parser.flags;
+    let mut enable = true;
+
+    loop {
+        match parser.next() {
+            Some(')') => return Ok(Token::SetFlags(flags)),
+            Some(':') => return Ok(Token::NonCapturing(flags)),
+            Some('-') if enable => enable = false,
+            Some('i') => flags.case_insensitive = enable,
+            Some('m') => flags.multi_line = enable,
+            Some('s') => flags.dot_all = enable,
+            Some('x') => flags.extended = enable,
+            Some('u') => flags.ascii = !enable,
+            None => return Err(PatternError::UnclosedGroup { offset }),
+            Some(_) => return Err(PatternError::UnknownGroup { offset }),
+        }
+    }
+}
+
+/// Reads a group name up to its closing `close`
+///
+/// Names start with a letter or `_` and go on with letters, digits and `_`.
+fn group_name(parser: &mut Parser, close: char, offset: usize) -> Result<String> {
+    let mut name = String::new();
+
+    loop {
+        match parser.next() {
+            Some(c) if c == close && !name.is_empty() => return Ok(name),
+            Some(c) if c == '_' || c.is_ascii_alphabetic() => name.push(c),
+            Some(c) if c.is_ascii_digit() && !name.is_empty() => name.push(c),
+            // Lookbehind assertions like `(?<=...)` aren't supported
+            Some('=' | '!') if name.is_empty() && close == '>' => {
+                return Err(PatternError::UnknownGroup { offset })
+            }
+            None => return Err(PatternError::UnclosedGroup { offset }),
+            Some(_) => return Err(PatternError::BadGroupName { offset }),
+        }
+    }
+}
+
+/// Skips `\Q` and `\E` markers, and whitespace and comments in `(?x)` mode
+pub(super) fn skip_trivia(parser: &mut Parser) {
+    loop {
+        if parser.lookahead(&['\\', 'E']) {
+            parser.pos += 2;
+            parser.quoting = false;
+        } else if parser.quoting {
+            return;
+        } else if parser.lookahead(&['\\', 'Q']) {
+            parser.pos += 2;
+            parser.quoting = true;
+        } else if parser.flags.extended && parser.peek().is_some_and(char::is_whitespace) {
+            parser.pos += 1;
+        } else if parser.flags.extended && parser.peek() == Some('#') {
+            // A comment runs to the end of the line
+            while parser.next().is_some_and(|c| c != '\n') {}
+        } else {
+            return;
+        }
+    }
+}
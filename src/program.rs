@@ -0,0 +1,483 @@
+use std::ops::Range;
+
+use crate::ast::{Class, Pattern};
+use crate::error::PatternError;
+use crate::parser::Syntax;
+
+/// A single instruction of a compiled pattern
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inst {
+    /// Match one literal character
+    Literal(char),
+    /// Match any character (`.`)
+    Any,
+    /// Match one character from a class
+    Class(Class),
+    /// Match the start of the line
+    Start,
+    /// Match the end of the line
+    End,
+    /// Match the start of the input or right after any `\n`
+    LineStart,
+    /// Match the end of the input or right before any `\n`
+    LineEnd,
+    /// Match the end of the input, or right before a `\n` that ends it
+    EndBeforeNewline,
+    /// Match a word boundary, or a non-boundary when negated
+    WordBoundary { negated: bool, ascii: bool },
+    /// Try the first target, and fall back to the second one on failure
+    Split(usize, usize),
+    /// Continue at another instruction
+    Jump(usize),
+    /// Record the current input position into a slot
+    Save(usize),
+    /// Fail if the input position didn't move since the given slot was saved
+    Progress(usize),
+    /// Match the text captured by a group again
+    Backreference(usize),
+    /// The whole pattern matched
+    Match,
+}
+
+/// The part of a pattern that a split was compiled from, to tell splits
+/// apart when tracing
+#[derive(Debug, Clone, PartialEq)]
+pub enum Origin {
+    /// The choice between branch `index` of an alternation of `count`
+    /// branches, counting from 1, and the branches after it
+    Branch { index: usize, count: usize },
+    /// The choice between another run of a repeated subpattern, compiled to
+    /// `body`, and going on past it
+    Repeat { greedy: bool, body: Range<usize> },
+}
+
+/// Options that change how a program matches its input
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Whether characters are decoded as UTF-8 (`true`) or matched as raw bytes
+    pub unicode: bool,
+    /// A byte that is never matched, and that `^` and `$` match around
+    ///
+    /// Setting this to `\n` lets a whole block of lines be searched at once
+    /// while every match still stays within a single line.
+    pub line_terminator: Option<u8>,
+    /// The dialect the pattern is written in
+    pub syntax: Syntax,
+    /// Whether `^` and `$` start out matching around every `\n`, like `(?m)`
+    ///
+    /// `\A` and `\z` always match only at the start and end of the input.
+    pub multi_line: bool,
+    /// Whether a `\r` right before the end of a line counts as part of the
+    /// line ending, so `$` matches before `\r\n`
+    pub crlf: bool,
+    /// Whether `\d`, `\w`, `\s`, `\b` and case folding start out ASCII-only
+    ///
+    /// Perl patterns can switch this with `(?-u)` and `(?u)`.
+    pub ascii: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            unicode: true,
+            line_terminator: None,
+            syntax: Syntax::Extended,
+            multi_line: false,
+            crlf: false,
+            ascii: false,
+        }
+    }
+}
+
+/// A compiled pattern, ready to be run by the matcher
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub insts: Vec<Inst>,
+    /// Number of capture groups, not counting the implicit whole-match group 0
+    pub groups: usize,
+    /// Name of each group by index, `None` for group 0 and unnamed groups
+    pub names: Vec<Option<String>>,
+    /// Total number of slots: two per group, plus loop progress markers
+    pub slots: usize,
+    pub config: Config,
+    /// Bytes a match can start with, or `None` if it can start anywhere
+    pub first_bytes: Option<Box<[bool; 256]>>,
+    /// Where the matcher remembers having tried each split, `None` for
+    /// other instructions
+    ///
+    /// Every split is remembered, unless the program has backreferences or
+    /// `\K`, which make the outcome depend on earlier captures.
+    pub memo: Vec<Option<MemoIndex>>,
+    /// Number of bits the matcher remembers per input position
+    pub memo_width: usize,
+    /// What each split was compiled from, `None` for other instructions
+    pub origins: Vec<Option<Origin>>,
+}
+
+/// Where a split keeps its bits in the memo of the matcher
+///
+/// Whether a split fails at an input position only depends on which of the
+/// loops around it started their iteration at that position, since only
+/// those can still fail their progress check. The loops are nested, so that
+/// is always a number of the innermost ones, and the split gets a bit for
+/// each count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoIndex {
+    /// The first bit of the split within an input position
+    pub bit: usize,
+    /// Progress markers of the loops around the split
+    pub guards: Vec<usize>,
+}
+
+/// Most instructions a program may have, since bounded repeats are unrolled
+const MAX_INSTS: usize = 1 << 20;
+
+/// Compiles a pattern tree into a program
+///
+/// The whole match is wrapped in group 0, so slots 0 and 1 always hold the
+/// span of a successful match. Patterns whose program would be larger than
+/// `MAX_INSTS` are rejected.
+pub fn compile(pattern: &Pattern, config: Config) -> Result<Program, PatternError> {
+    if size(pattern) > MAX_INSTS - 3 {
+        return Err(PatternError::TooLarge { offset: 0 });
+    }
+
+    let groups = pattern.group_count();
+    let mut compiler = Compiler {
+        insts: vec![],
+        origins: vec![],
+        slots: (groups + 1) * 2,
+    };
+
+    compiler.push(Inst::Save(0));
+    compiler.compile(pattern);
+    compiler.push(Inst::Save(1));
+    compiler.push(Inst::Match);
+
+    let first_bytes = first_bytes(&compiler.insts, &config);
+    let (memo, memo_width) = memo(&compiler.insts, (groups + 1) * 2);
+    let mut names = vec![None; groups + 1];
+    pattern.group_names(&mut names);
+
+    Ok(Program {
+        insts: compiler.insts,
+        groups,
+        names,
+        slots: compiler.slots,
+        config,
+        first_bytes,
+        memo,
+        memo_width,
+        origins: compiler.origins,
+    })
+}
+
+/// Lays out the bits of every split in the memo, as described on
+/// [`Program::memo`], returning them with the number of bits per position
+///
+/// Slots from `guards` on are the progress markers of loops. Each loop body
+/// runs from the `Save` of its marker to the matching `Progress`.
+fn memo(insts: &[Inst], guards: usize) -> (Vec<Option<MemoIndex>>, usize) {
+    // Past the first instruction, `Save(0)` is a `\K`
+    let captures_matter = insts[1..]
+        .iter()
+        .any(|inst| matches!(inst, Inst::Backreference(_) | Inst::Save(0)));
+    if captures_matter {
+        return (vec![None; insts.len()], 0);
+    }
+
+    let mut around: Vec<Vec<usize>> = vec![vec![]; insts.len()];
+    for (start, inst) in insts.iter().enumerate() {
+        if let Inst::Save(slot) = inst {
+            if *slot < guards {
+                continue;
+            }
+            let end = insts[start..]
+                .iter()
+                .position(|inst| *inst == Inst::Progress(*slot))
+                .map_or(insts.len(), |len| start + len);
+            for guards in &mut around[start..end] {
+                guards.push(*slot);
+            }
+        }
+    }
+
+    let mut width = 0;
+    let memo = insts
+        .iter()
+        .zip(around)
+        .map(|(inst, guards)| {
+            let Inst::Split(..) = inst else {
+                return None;
+            };
+            let index = MemoIndex { bit: width, guards };
+            width += index.guards.len() + 1;
+            Some(index)
+        })
+        .collect();
+    (memo, width)
+}
+
+/// Counts the instructions a pattern compiles to, saturating on overflow
+fn size(pattern: &Pattern) -> usize {
+    match pattern {
+        Pattern::Empty => 0,
+        Pattern::Literal(_)
+        | Pattern::Wildcard
+        | Pattern::Class(_)
+        | Pattern::Start
+        | Pattern::End
+        | Pattern::LineStart
+        | Pattern::LineEnd
+        | Pattern::EndBeforeNewline
+        | Pattern::ResetStart
+        | Pattern::WordBoundary { .. }
+        | Pattern::Backreference(_) => 1,
+        Pattern::Group { pattern, .. } => size(pattern).saturating_add(2),
+        Pattern::Concat(items) => items.iter().map(size).fold(0, usize::saturating_add),
+        // A split and a jump before every branch but the last
+        Pattern::Alternation(branches) => branches
+            .iter()
+            .map(|branch| size(branch).saturating_add(2))
+            .fold(0, usize::saturating_add),
+        Pattern::Repeat {
+            min, max, pattern, ..
+        } => {
+            let body = size(pattern);
+            let optional = match max {
+                // A split, the body with its progress check, and a jump back
+                None => body.saturating_add(4),
+                Some(max) => ((max - min) as usize).saturating_mul(body.saturating_add(1)),
+            };
+            (*min as usize)
+                .saturating_mul(body)
+                .saturating_add(optional)
+        }
+    }
+}
+
+/// Collects the set of bytes that the first consumed byte of a match can be
+///
+/// The searcher uses this to skip over positions where no match can start.
+/// Returns `None` when the program can match the empty string, or when the
+/// set can't be narrowed down cheaply.
+fn first_bytes(insts: &[Inst], config: &Config) -> Option<Box<[bool; 256]>> {
+    let mut set = Box::new([false; 256]);
+    let mut seen = vec![false; insts.len()];
+    let mut stack = vec![0];
+
+    // Follow every path through zero-width instructions to the first byte read
+    while let Some(pc) = stack.pop() {
+        if std::mem::replace(&mut seen[pc], true) {
+            continue;
+        }
+
+        match &insts[pc] {
+            Inst::Literal(c) => {
+                let mut buf = [0; 4];
+                set[c.encode_utf8(&mut buf).as_bytes()[0] as usize] = true;
+            }
+            Inst::Any => set.fill(true),
+            Inst::Class(class) => {
+                for byte in 0..=255u8 {
+                    set[byte as usize] |= if byte < 0x80 || !config.unicode {
+                        class.contains(byte as char)
+                    } else {
+                        // Any multi-byte character could be a member
+                        true
+                    };
+                }
+            }
+            Inst::Start
+            | Inst::End
+            | Inst::LineStart
+            | Inst::LineEnd
+            | Inst::EndBeforeNewline
+            | Inst::WordBoundary { .. }
+            | Inst::Save(_)
+            | Inst::Progress(_) => stack.push(pc + 1),
+            Inst::Split(first, second) => stack.extend([*second, *first]),
+            Inst::Jump(target) => stack.push(*target),
+            Inst::Backreference(_) | Inst::Match => return None,
+        }
+    }
+
+    if let Some(terminator) = config.line_terminator {
+        set[terminator as usize] = false;
+    }
+
+    Some(set)
+}
+
+struct Compiler {
+    insts: Vec<Inst>,
+    origins: Vec<Option<Origin>>,
+    slots: usize,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> usize {
+        self.insts.push(inst);
+        self.origins.push(None);
+        self.insts.len() - 1
+    }
+
+    /// Points a previously emitted jump or split at a new target
+    fn patch(&mut self, at: usize, target: usize) {
+        match &mut self.insts[at] {
+            Inst::Jump(to) => *to = target,
+            Inst::Split(_, to) => *to = target,
+            _ => unreachable!("only jumps and splits are patched"),
+        }
+    }
+
+    fn compile(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Empty => {}
+            Pattern::Literal(c) => {
+                self.push(Inst::Literal(*c));
+            }
+            Pattern::Wildcard => {
+                self.push(Inst::Any);
+            }
+            Pattern::Class(class) => {
+                self.push(Inst::Class(class.clone()));
+            }
+            Pattern::Start => {
+                self.push(Inst::Start);
+            }
+            Pattern::End => {
+                self.push(Inst::End);
+            }
+            Pattern::LineStart => {
+                self.push(Inst::LineStart);
+            }
+            Pattern::LineEnd => {
+                self.push(Inst::LineEnd);
+            }
+            Pattern::EndBeforeNewline => {
+                self.push(Inst::EndBeforeNewline);
+            }
+            Pattern::ResetStart => {
+                self.push(Inst::Save(0));
+            }
+            Pattern::WordBoundary { negated, ascii } => {
+                self.push(Inst::WordBoundary {
+                    negated: *negated,
+                    ascii: *ascii,
+                });
+            }
+            Pattern::Backreference(index) => {
+                self.push(Inst::Backreference(*index));
+            }
+            Pattern::Group { index, pattern, .. } => {
+                self.push(Inst::Save(index * 2));
+                self.compile(pattern);
+                self.push(Inst::Save(index * 2 + 1));
+            }
+            Pattern::Concat(items) => {
+                for item in items {
+                    self.compile(item);
+                }
+            }
+            Pattern::Alternation(branches) => {
+                // split L1, next; L1: branch; jump end; next: split L2, ...
+                let mut jumps = vec![];
+
+                for (i, branch) in branches.iter().enumerate() {
+                    if i + 1 < branches.len() {
+                        let split = self.push(Inst::Split(self.insts.len() + 1, 0));
+                        self.origins[split] = Some(Origin::Branch {
+                            index: i + 1,
+                            count: branches.len(),
+                        });
+                        self.compile(branch);
+                        jumps.push(self.push(Inst::Jump(0)));
+                        let next = self.insts.len();
+                        self.patch(split, next);
+                    } else {
+                        self.compile(branch);
+                    }
+                }
+
+                let end = self.insts.len();
+                for jump in jumps {
+                    self.patch(jump, end);
+                }
+            }
+            Pattern::Repeat {
+                min,
+                max,
+                greedy,
+                pattern,
+            } => {
+                for _ in 0..*min {
+                    self.compile(pattern);
+                }
+
+                match max {
+                    None => self.compile_star(pattern, *greedy),
+                    Some(max) => {
+                        for _ in *min..*max {
+                            self.compile_optional(pattern, *greedy);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Emits a split that either runs `pattern` or skips it
+    fn compile_optional(&mut self, pattern: &Pattern, greedy: bool) {
+        let split = self.push(Inst::Split(0, 0));
+        let body = self.insts.len();
+        self.compile(pattern);
+        let end = self.insts.len();
+
+        self.insts[split] = self.split(body, end, greedy);
+        self.origins[split] = Some(Origin::Repeat {
+            greedy,
+            body: body..end,
+        });
+    }
+
+    /// Emits a loop that runs `pattern` zero or more times
+    ///
+    /// Patterns that can match the empty string get a progress check, so an
+    /// iteration that consumed nothing can't loop forever.
+    fn compile_star(&mut self, pattern: &Pattern, greedy: bool) {
+        let guard = pattern.can_be_empty().then(|| {
+            self.slots += 1;
+            self.slots - 1
+        });
+
+        let split = self.push(Inst::Split(0, 0));
+        let body = self.insts.len();
+
+        if let Some(slot) = guard {
+            self.push(Inst::Save(slot));
+        }
+        self.compile(pattern);
+        if let Some(slot) = guard {
+            self.push(Inst::Progress(slot));
+        }
+
+        let jump = self.push(Inst::Jump(split));
+        let end = self.insts.len();
+
+        self.insts[split] = self.split(body, end, greedy);
+        self.origins[split] = Some(Origin::Repeat {
+            greedy,
+            body: body..jump,
+        });
+    }
+
+    /// Builds a split that prefers `body` when greedy and `skip` otherwise
+    fn split(&self, body: usize, skip: usize, greedy: bool) -> Inst {
+        if greedy {
+            Inst::Split(body, skip)
+        } else {
+            Inst::Split(skip, body)
+        }
+    }
+}
@@ -0,0 +1,525 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::{Index, Range};
+
+use crate::error::PatternError;
+use crate::matcher::{self, Slots};
+use crate::parser;
+use crate::program::{self, Config, Program};
+use crate::replace::Template;
+
+/// A compiled regular expression
+///
+/// This is the library entry point: it parses a regular expression in the
+/// configured dialect (extended by default), compiles it once, and runs it
+/// against any number of haystacks.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    pattern: String,
+    program: Program,
+}
+
+/// An error from compiling a regular expression
+///
+/// It displays the pattern with a caret under the position of the error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pattern: String,
+    kind: PatternError,
+}
+
+/// A single match of a regex in a haystack
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Match<'h> {
+    haystack: &'h str,
+    start: usize,
+    end: usize,
+}
+
+/// The capture groups of a single match
+///
+/// Group 0 is always the whole match; groups that didn't participate in the
+/// match are `None`.
+#[derive(Debug, Clone)]
+pub struct Captures<'h> {
+    haystack: &'h str,
+    slots: Slots,
+}
+
+impl Regex {
+    /// Compiles a pattern with the default configuration
+    pub fn new(pattern: &str) -> Result<Regex, Error> {
+        Regex::with_config(pattern, Config::default())
+    }
+
+    /// Compiles a pattern with an explicit configuration
+    ///
+    /// The `&str` methods expect `config.unicode` to be set, so that matches
+    /// always fall on character boundaries.
+    pub fn with_config(pattern: &str, config: Config) -> Result<Regex, Error> {
+        let error = |kind| Error {
+            pattern: pattern.to_string(),
+            kind,
+        };
+        let parsed = parser::parse(pattern, &config).map_err(error)?;
+
+        Ok(Regex {
+            pattern: pattern.to_string(),
+            program: program::compile(&parsed, config).map_err(error)?,
+        })
+    }
+
+    /// Returns the pattern this regex was compiled from
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns the compiled program, for running the matcher on raw bytes
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Returns the number of capture groups, including the whole match
+    pub fn captures_len(&self) -> usize {
+        self.program.groups + 1
+    }
+
+    /// Checks if the regex matches anywhere in the haystack
+    pub fn is_match(&self, haystack: &str) -> bool {
+        self.find(haystack).is_some()
+    }
+
+    /// Returns the leftmost match in the haystack
+    pub fn find<'h>(&self, haystack: &'h str) -> Option<Match<'h>> {
+        self.captures(haystack).and_then(|captures| captures.get(0))
+    }
+
+    /// Returns an iterator over all non-overlapping matches
+    pub fn find_iter<'r, 'h>(&'r self, haystack: &'h str) -> Matches<'r, 'h> {
+        Matches(self.captures_iter(haystack))
+    }
+
+    /// Returns the capture groups of the leftmost match
+    pub fn captures<'h>(&self, haystack: &'h str) -> Option<Captures<'h>> {
+        self.captures_iter(haystack).next()
+    }
+
+    /// Returns an iterator over the capture groups of all non-overlapping matches
+    pub fn captures_iter<'r, 'h>(&'r self, haystack: &'h str) -> CaptureMatches<'r, 'h> {
+        CaptureMatches {
+            regex: self,
+            haystack,
+            pos: 0,
+            last_end: None,
+        }
+    }
+
+    /// Replaces the leftmost match with `replacement`, inserted literally
+    pub fn replace<'h>(&self, haystack: &'h str, replacement: &str) -> Cow<'h, str> {
+        match self.find(haystack) {
+            Some(found) => Cow::Owned(
+                [
+                    &haystack[..found.start],
+                    replacement,
+                    &haystack[found.end..],
+                ]
+                .concat(),
+            ),
+            None => Cow::Borrowed(haystack),
+        }
+    }
+
+    /// Replaces every non-overlapping match with an expansion of `template`
+    ///
+    /// Unlike with [`Regex::replace`], `$1`, `${name}` and `\1` in the
+    /// template insert capture groups; see [`Template`] for the full syntax.
+    pub fn replace_all<'h>(&self, haystack: &'h str, template: &str) -> Cow<'h, str> {
+        let template = Template::new(template, &self.program);
+        let mut replaced = vec![];
+        let mut last = 0;
+        let mut found = false;
+
+        for captures in self.captures_iter(haystack) {
+            found = true;
+            let (start, end) = (captures.slots[0].unwrap(), captures.slots[1].unwrap());
+            replaced.extend_from_slice(&haystack.as_bytes()[last..start]);
+            template.expand(haystack.as_bytes(), &captures.slots, &mut replaced);
+            last = end;
+        }
+
+        if !found {
+            return Cow::Borrowed(haystack);
+        }
+        replaced.extend_from_slice(&haystack.as_bytes()[last..]);
+        Cow::Owned(String::from_utf8_lossy(&replaced).into_owned())
+    }
+
+    /// Returns an iterator over the substrings between matches
+    pub fn split<'r, 'h>(&'r self, haystack: &'h str) -> Split<'r, 'h> {
+        Split {
+            matches: self.find_iter(haystack),
+            haystack,
+            last: Some(0),
+        }
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+impl Error {
+    /// Returns what went wrong, along with its offset in the pattern
+    pub fn kind(&self) -> &PatternError {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.kind.render(&self.pattern))
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl<'h> Match<'h> {
+    /// Byte offset where the match starts
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Byte offset right after the end of the match
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Byte range of the match in the haystack
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// The matched text
+    pub fn as_str(&self) -> &'h str {
+        &self.haystack[self.start..self.end]
+    }
+}
+
+impl<'h> Captures<'h> {
+    /// Returns capture group `index`, or `None` if it didn't participate
+    pub fn get(&self, index: usize) -> Option<Match<'h>> {
+        let start = (*self.slots.get(index * 2)?)?;
+        let end = (*self.slots.get(index * 2 + 1)?)?;
+
+        Some(Match {
+            haystack: self.haystack,
+            start,
+            end,
+        })
+    }
+
+    /// Returns the number of groups, including the whole match
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.slots.len() / 2
+    }
+
+    /// Returns an iterator over all groups, in order
+    pub fn iter(&self) -> impl Iterator<Item = Option<Match<'h>>> + '_ {
+        (0..self.len()).map(|index| self.get(index))
+    }
+}
+
+impl<'h> Index<usize> for Captures<'h> {
+    type Output = str;
+
+    /// Returns the text of a group, panicking if it didn't participate
+    fn index(&self, index: usize) -> &str {
+        self.get(index)
+            .map(|group| group.as_str())
+            .unwrap_or_else(|| panic!("no group at index '{}'", index))
+    }
+}
+
+/// Iterator over the capture groups of successive matches
+///
+/// An empty match right where the previous match ended is skipped, so the
+/// iterator always makes progress.
+pub struct CaptureMatches<'r, 'h> {
+    regex: &'r Regex,
+    haystack: &'h str,
+    pos: usize,
+    last_end: Option<usize>,
+}
+
+impl<'r, 'h> Iterator for CaptureMatches<'r, 'h> {
+    type Item = Captures<'h>;
+
+    fn next(&mut self) -> Option<Captures<'h>> {
+        loop {
+            if self.pos > self.haystack.len() {
+                return None;
+            }
+
+            let mut slots =
+                matcher::find_at(&self.regex.program, self.haystack.as_bytes(), self.pos)?;
+            let (start, end) = (slots[0]?, slots[1]?);
+
+            if start == end && self.last_end == Some(end) {
+                // Step over the next character and try again
+                self.pos = end
+                    + self.haystack[end..]
+                        .chars()
+                        .next()
+                        .map_or(1, char::len_utf8);
+                continue;
+            }
+
+            self.pos = end;
+            self.last_end = Some(end);
+
+            // Drop the loop progress markers that follow the group slots
+            slots.truncate(self.regex.captures_len() * 2);
+            return Some(Captures {
+                haystack: self.haystack,
+                slots,
+            });
+        }
+    }
+}
+
+/// Iterator over successive non-overlapping matches
+pub struct Matches<'r, 'h>(CaptureMatches<'r, 'h>);
+
+impl<'r, 'h> Iterator for Matches<'r, 'h> {
+    type Item = Match<'h>;
+
+    fn next(&mut self) -> Option<Match<'h>> {
+        self.0.next().and_then(|captures| captures.get(0))
+    }
+}
+
+/// Iterator over the substrings between matches
+pub struct Split<'r, 'h> {
+    matches: Matches<'r, 'h>,
+    haystack: &'h str,
+    /// Where the next piece starts, or `None` once the last piece was returned
+    last: Option<usize>,
+}
+
+impl<'r, 'h> Iterator for Split<'r, 'h> {
+    type Item = &'h str;
+
+    fn next(&mut self) -> Option<&'h str> {
+        let last = self.last?;
+
+        match self.matches.next() {
+            Some(found) => {
+                self.last = Some(found.end);
+                Some(&self.haystack[last..found.start])
+            }
+            None => {
+                self.last = None;
+                Some(&self.haystack[last..])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::parser::Syntax;
+
+    fn perl(pattern: &str) -> Regex {
+        let config = Config {
+            syntax: Syntax::Perl,
+            ..Config::default()
+        };
+        Regex::with_config(pattern, config).unwrap()
+    }
+
+    #[test]
+    fn finds_leftmost_first_match() {
+        let regex = Regex::new("a+|b").unwrap();
+        assert!(regex.is_match("xxbaa"));
+        assert_eq!(regex.find("xxbaa").unwrap().range(), 2..3);
+        assert_eq!(
+            Regex::new("ab|abc").unwrap().find("abc").unwrap().as_str(),
+            "ab"
+        );
+        assert!(regex.find("xyz").is_none());
+    }
+
+    #[test]
+    fn captures_groups_by_number() {
+        let regex = Regex::new(r"(\w+)@(\w+)(!)?").unwrap();
+        let captures = regex.captures("mail bob@example now").unwrap();
+        assert_eq!(&captures[0], "bob@example");
+        assert_eq!(&captures[1], "bob");
+        assert_eq!(&captures[2], "example");
+        assert!(captures.get(3).is_none());
+        assert_eq!(captures.len(), 4);
+    }
+
+    #[test]
+    fn iterates_over_empty_matches_once() {
+        let regex = Regex::new("a*").unwrap();
+        let matches: Vec<_> = regex.find_iter("baab").map(|m| m.range()).collect();
+        assert_eq!(matches, [0..0, 1..3, 4..4]);
+    }
+
+    #[test]
+    fn replaces_with_group_references() {
+        let regex = perl(r"(?<user>\w+)@(\w+)");
+        assert_eq!(
+            regex.replace_all("a@b c@d", "$2 at ${user}"),
+            "b at a d at c"
+        );
+        assert_eq!(regex.replace_all("a@b", r"\1$$\\"), r"a$\");
+        assert_eq!(regex.replace("a@b c@d", "x"), "x c@d");
+        assert_eq!(regex.replace_all("none", "x"), "none");
+    }
+
+    #[test]
+    fn replace_all_skips_empty_match_after_match() {
+        let regex = Regex::new("a*").unwrap();
+        assert_eq!(regex.replace_all("aaa", "X"), "X");
+        assert_eq!(regex.replace_all("abc", "X"), "XbXcX");
+    }
+
+    #[test]
+    fn splits_around_matches() {
+        let regex = Regex::new(", *").unwrap();
+        let parts: Vec<_> = regex.split("a, b,,c").collect();
+        assert_eq!(parts, ["a", "b", "", "c"]);
+    }
+
+    #[test]
+    fn matches_posix_classes() {
+        let regex = Regex::new("^[[:alpha:]]+[[:digit:]]?$").unwrap();
+        assert!(regex.is_match("ab"));
+        assert!(regex.is_match("Éte7"));
+        assert!(!regex.is_match("a_b"));
+        assert!(Regex::new("^[[:punct:][:space:]]+$")
+            .unwrap()
+            .is_match("!? ,"));
+        assert!(Regex::new(r"a[\]b").unwrap().is_match(r"a\b"));
+    }
+
+    #[test]
+    fn rejects_huge_repeats() {
+        let error = Regex::new("a{40000}").unwrap_err();
+        assert_eq!(*error.kind(), PatternError::RepeatTooLarge { offset: 1 });
+
+        let error = Regex::new("((a{1000}){1000}){1000}").unwrap_err();
+        assert_eq!(*error.kind(), PatternError::TooLarge { offset: 0 });
+
+        assert!(Regex::new("a{32767}").is_ok());
+    }
+
+    #[test]
+    fn backtracking_stays_polynomial() {
+        let haystack = "a".repeat(200);
+        for pattern in ["(a|aa)*c", "(a*)*c", "(a?|aa)+c", ".*.*.*c"] {
+            assert!(
+                !Regex::new(pattern).unwrap().is_match(&haystack),
+                "{}",
+                pattern
+            );
+        }
+        assert!(Regex::new("(a|aa)*c").unwrap().is_match(&(haystack + "c")));
+    }
+
+    #[test]
+    fn backtracking_in_loop_bodies_stays_linear() {
+        let haystack = "a".repeat(20_000);
+        let started = Instant::now();
+        for pattern in [
+            "(a*)*b",
+            "(a|aa)*a long literal that isn't there",
+            "((a?)*)*b",
+        ] {
+            assert!(
+                !Regex::new(pattern).unwrap().is_match(&haystack),
+                "{}",
+                pattern
+            );
+        }
+        // Exponential or quadratic backtracking takes minutes
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        assert_eq!(
+            Regex::new("a(b").unwrap_err().to_string(),
+            "regex parse error:\n    a(b\n     ^\nerror: unclosed group"
+        );
+        // The caret counts characters, not bytes
+        assert_eq!(
+            Regex::new("é[b").unwrap_err().to_string(),
+            "regex parse error:\n    é[b\n     ^\nerror: unclosed character class"
+        );
+    }
+
+    #[test]
+    fn matches_unicode_properties() {
+        let greek = Regex::new(r"^\p{Greek}+$").unwrap();
+        assert!(greek.is_match("λόγος"));
+        assert!(!greek.is_match("logos"));
+        assert!(Regex::new(r"^\P{Greek}+$").unwrap().is_match("logos"));
+        // POSIX brackets take backslashes literally, so this needs Perl syntax
+        assert!(perl(r"^[\p{L}\p{Nd}]+$").is_match("ab٣"));
+
+        let error = Regex::new(r"a\p{Klingon}").unwrap_err();
+        assert_eq!(*error.kind(), PatternError::UnknownProperty { offset: 1 });
+    }
+
+    #[test]
+    fn ascii_mode_limits_shorthand_classes() {
+        let unicode = Regex::new(r"^\w\d$").unwrap();
+        assert!(unicode.is_match("é٣"));
+
+        let ascii = Regex::with_config(
+            r"^\w\d$",
+            Config {
+                ascii: true,
+                ..Config::default()
+            },
+        )
+        .unwrap();
+        assert!(ascii.is_match("a3"));
+        assert!(!ascii.is_match("é3"));
+        assert!(!ascii.is_match("a٣"));
+
+        // Perl patterns switch it inline
+        assert!(!perl(r"^(?-u)\w$").is_match("é"));
+        assert!(perl(r"^(?-u:\d)\w$").is_match("3é"));
+    }
+
+    #[test]
+    fn multi_line_anchors() {
+        let haystack = "one\ntwo\n";
+        assert!(Regex::new("^two").unwrap().find(haystack).is_none());
+        assert_eq!(perl("(?m)^two$").find(haystack).unwrap().range(), 4..7);
+        assert_eq!(perl(r"(?m)\Atwo").find(haystack), None);
+        assert_eq!(perl(r"(?m)two\n\z").find(haystack).unwrap().range(), 4..8);
+
+        let crlf = Regex::with_config(
+            "(?m)one$",
+            Config {
+                syntax: Syntax::Perl,
+                crlf: true,
+                ..Config::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(crlf.find("one\r\ntwo").unwrap().range(), 0..3);
+        assert!(perl("(?m)one$").find("one\r\ntwo").is_none());
+    }
+}
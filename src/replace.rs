@@ -0,0 +1,151 @@
+use crate::program::Program;
+
+/// A replacement string with references to capture groups
+///
+/// `$1` or `\1` insert a group by number, `${1}` and `${name}` by number or
+/// name, and `$$` and `\\` insert a literal `$` and `\`. Any other `$` or `\`
+/// is taken literally. Groups that don't exist or didn't participate in the
+/// match insert nothing.
+#[derive(Debug, Clone)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(String),
+    Group(usize),
+}
+
+impl Template {
+    /// Parses a template, resolving group names with the groups of `program`
+    pub fn new(template: &str, program: &Program) -> Template {
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut rest = template;
+
+        while let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+
+            let reference = match c {
+                '$' => reference(rest, program),
+                '\\' => match rest.chars().next() {
+                    Some(digit @ '0'..='9') => Some((digit.to_digit(10).map(|d| d as usize), 1)),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            match reference {
+                Some((group, len)) => {
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    // Groups that don't exist are dropped, since they'd insert nothing
+                    if let Some(group) = group.filter(|&group| group <= program.groups) {
+                        parts.push(Part::Group(group));
+                    }
+                    rest = &rest[len..];
+                }
+                // `$$` and `\\` stand for the character itself
+                None if matches!(c, '$' | '\\') && rest.starts_with(c) => {
+                    literal.push(c);
+                    rest = &rest[1..];
+                }
+                None => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Template { parts }
+    }
+
+    /// Appends the replacement for one match to `dst`
+    ///
+    /// `slots` holds the spans of the groups of the match in `haystack`, two
+    /// slots per group like the matcher returns them.
+    pub fn expand(&self, haystack: &[u8], slots: &[Option<usize>], dst: &mut Vec<u8>) {
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => dst.extend_from_slice(text.as_bytes()),
+                Part::Group(group) => {
+                    if let (Some(start), Some(end)) = (slots[group * 2], slots[group * 2 + 1]) {
+                        dst.extend_from_slice(&haystack[start..end]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Reads the group reference after a `$`
+///
+/// Returns the group, if there's one with that number or name, and the length
+/// of the reference. Returns `None` when the `$` doesn't start a reference.
+fn reference(rest: &str, program: &Program) -> Option<(Option<usize>, usize)> {
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 {
+        return Some((rest[..digits].parse().ok(), digits));
+    }
+
+    let (name, _) = rest.strip_prefix('{')?.split_once('}')?;
+    let group = match name.parse() {
+        Ok(index) => Some(index),
+        Err(_) => program
+            .names
+            .iter()
+            .position(|group| group.as_deref() == Some(name)),
+    };
+    Some((group, name.len() + 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Regex, Syntax};
+
+    /// Expands `template` for the first match of a Perl `pattern`
+    fn expand(pattern: &str, template: &str, haystack: &str) -> String {
+        let config = Config {
+            syntax: Syntax::Perl,
+            ..Config::default()
+        };
+        let regex = Regex::with_config(pattern, config).unwrap();
+        let slots = crate::matcher::find_at(regex.program(), haystack.as_bytes(), 0).unwrap();
+
+        let mut expanded = vec![];
+        Template::new(template, regex.program()).expand(haystack.as_bytes(), &slots, &mut expanded);
+        String::from_utf8(expanded).unwrap()
+    }
+
+    #[test]
+    fn group_references() {
+        assert_eq!(
+            expand(r"(\w+) (\w+)", "$2 $1", "hello world"),
+            "world hello"
+        );
+        assert_eq!(
+            expand(r"(\w+) (\w+)", r"\2-\1", "hello world"),
+            "world-hello"
+        );
+        assert_eq!(expand(r"(\w+)", "${1}s", "cat"), "cats");
+        assert_eq!(expand(r"(?<word>\w+)", "<${word}>", "cat"), "<cat>");
+        assert_eq!(expand(r"\w+", "[$0]", "cat"), "[cat]");
+    }
+
+    #[test]
+    fn escapes_and_literals() {
+        assert_eq!(expand("a", "$$1", "a"), "$1");
+        assert_eq!(expand("a", r"\\1", "a"), r"\1");
+        assert_eq!(expand("a", "$ and \\", "a"), "$ and \\");
+        assert_eq!(expand("a", "${oops", "a"), "${oops");
+    }
+
+    #[test]
+    fn missing_groups_insert_nothing() {
+        assert_eq!(expand("(a)|(b)", "[$1|$2]", "b"), "[|b]");
+        assert_eq!(expand("a", "[$7${nope}]", "a"), "[]");
+    }
+}
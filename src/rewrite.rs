@@ -0,0 +1,369 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::ops::Range;
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+use std::process;
+
+use crate::encoding;
+use crate::searcher::{Edit, Searcher, State};
+
+/// Lines of unchanged context around each hunk of a diff
+const CONTEXT: usize = 3;
+
+/// How `--replace` edits files instead of printing lines
+pub struct EditOptions {
+    /// Keep the original file under its name followed by this (`--in-place=SUFFIX`)
+    pub backup_suffix: Option<String>,
+    /// Print a unified diff instead of writing the file (`--dry-run`)
+    pub dry_run: bool,
+}
+
+/// Applies the replacements of `searcher` to a file
+///
+/// The new contents are written to a temporary file next to the original,
+/// which then takes its place with a rename, so readers only ever see the
+/// old or the new file. The file keeps its permissions and its encoding,
+/// which is transcoded like in a search. With `dry_run`, the changes are
+/// printed to `out` as a unified diff of the transcoded text instead.
+pub fn edit_file<W: Write>(
+    searcher: &Searcher,
+    path: &str,
+    options: &EditOptions,
+    out: &mut W,
+) -> io::Result<State> {
+    let (input, original) = encoding::decode_all(&fs::read(path)?, searcher.encoding())?;
+    let mut state = State::default();
+
+    // Lines whose replacement is the line itself still match, but change nothing
+    let mut edits = searcher.edits(&input, &mut state);
+    edits.retain(|edit| edit.line != input[edit.range.clone()]);
+    if edits.is_empty() {
+        return Ok(state);
+    }
+
+    if options.dry_run {
+        write_diff(path, &input, &edits, searcher.terminator(), out)?;
+    } else {
+        replace_file(
+            Path::new(path),
+            &encoding::encode(&apply(&input, &edits), original)?,
+            options.backup_suffix.as_deref(),
+        )?;
+    }
+    Ok(state)
+}
+
+/// Builds the new contents of an input from its edits
+fn apply(input: &[u8], edits: &[Edit]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len());
+    let mut last = 0;
+
+    for edit in edits {
+        output.extend_from_slice(&input[last..edit.range.start]);
+        output.extend_from_slice(&edit.line);
+        last = edit.range.end;
+    }
+
+    output.extend_from_slice(&input[last..]);
+    output
+}
+
+/// Atomically replaces a file with new contents, keeping its permissions
+///
+/// A symbolic link is followed, so the file it points to is edited rather
+/// than replaced by a regular file.
+fn replace_file(path: &Path, contents: &[u8], backup_suffix: Option<&str>) -> io::Result<()> {
+    let path = fs::canonicalize(path)?;
+    let permissions = fs::metadata(&path)?.permissions();
+
+    // The temporary file must be on the same file system for the rename
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.{}.tmp", name, process::id()));
+
+    let result = (|| {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        // Others never get to read the new contents of a private file. The
+        // umask may still clear some bits, which are set again below.
+        #[cfg(unix)]
+        options.mode(permissions.mode());
+
+        let mut file = options.open(&temp)?;
+        file.set_permissions(permissions)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+
+        if let Some(suffix) = backup_suffix {
+            let mut backup = path.clone().into_os_string();
+            backup.push(suffix);
+            fs::copy(&path, backup)?;
+        }
+        fs::rename(&temp, &path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Prints the edits of an input as a unified diff, with `path` as the name of
+/// both the old and the new file
+fn write_diff<W: Write>(
+    path: &str,
+    input: &[u8],
+    edits: &[Edit],
+    terminator: u8,
+    out: &mut W,
+) -> io::Result<()> {
+    let lines = lines(input, terminator);
+    // Only the last line can lack a terminator
+    let unterminated = |i: usize| i + 1 == lines.len() && !input.ends_with(&[terminator]);
+    writeln!(out, "--- {}\n+++ {}", path, path)?;
+
+    // The indexes of the first and last old lines of each edit, which span
+    // several lines with -U
+    let spans: Vec<(usize, usize)> = edits
+        .iter()
+        .map(|edit| {
+            let first = edit.line_number as usize - 1;
+            let covered = input[edit.range.clone()]
+                .iter()
+                .filter(|&&b| b == terminator)
+                .count();
+            (first, first + covered)
+        })
+        .collect();
+
+    // Line numbers in the new file move by the lines each edit added
+    let mut offset: isize = 0;
+    let mut done = 0;
+    while done < edits.len() {
+        // A hunk takes edits until the unchanged lines between two of them
+        // are more than its context on both sides
+        let len = 1 + spans[done..]
+            .windows(2)
+            .take_while(|pair| pair[1].0 - pair[0].1 <= 2 * CONTEXT + 1)
+            .count();
+        let hunk = &edits[done..done + len];
+        let hunk_spans = &spans[done..done + len];
+        done += len;
+
+        let first = hunk_spans[0].0;
+        let last = hunk_spans[len - 1].1;
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(lines.len());
+
+        let mut body = vec![];
+        let mut old_count = 0;
+        let mut new_count = 0;
+        let mut edits = hunk.iter().zip(hunk_spans).peekable();
+        let mut i = start;
+        while i < end {
+            // A run of edited lines shows all old lines, then all new ones
+            let mut new_lines = vec![];
+            while let Some((edit, &(_, last))) = edits.next_if(|(_, span)| span.0 == i) {
+                for line in i..=last {
+                    diff_line(
+                        &mut body,
+                        b'-',
+                        &input[lines[line].clone()],
+                        unterminated(line),
+                    );
+                }
+                old_count += last + 1 - i;
+                new_lines.push((edit, last));
+                i = last + 1;
+            }
+
+            if new_lines.is_empty() {
+                diff_line(&mut body, b' ', &input[lines[i].clone()], unterminated(i));
+                old_count += 1;
+                new_count += 1;
+                i += 1;
+                continue;
+            }
+
+            for (edit, last) in new_lines {
+                let parts: Vec<&[u8]> = edit.line.split(|&b| b == terminator).collect();
+                for (j, part) in parts.iter().enumerate() {
+                    diff_line(
+                        &mut body,
+                        b'+',
+                        part,
+                        j + 1 == parts.len() && unterminated(last),
+                    );
+                }
+                new_count += parts.len();
+            }
+        }
+
+        writeln!(
+            out,
+            "@@ -{},{} +{},{} @@",
+            start + 1,
+            old_count,
+            start as isize + 1 + offset,
+            new_count
+        )?;
+        out.write_all(&body)?;
+        offset += new_count as isize - old_count as isize;
+    }
+
+    Ok(())
+}
+
+/// Adds one line of a diff, marking the last line of a file that doesn't
+/// end with a terminator
+fn diff_line(body: &mut Vec<u8>, marker: u8, line: &[u8], missing_newline: bool) {
+    body.push(marker);
+    body.extend_from_slice(line);
+    body.push(b'\n');
+    if missing_newline {
+        body.extend_from_slice(b"\\ No newline at end of file\n");
+    }
+}
+
+/// Splits an input into the ranges of its lines, without their terminators
+fn lines(input: &[u8], terminator: u8) -> Vec<Range<usize>> {
+    let mut lines = vec![];
+    let mut start = 0;
+
+    for (i, &b) in input.iter().enumerate() {
+        if b == terminator {
+            lines.push(start..i);
+            start = i + 1;
+        }
+    }
+    if start < input.len() {
+        lines.push(start..input.len());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(line_number: u64, range: Range<usize>, line: &str) -> Edit {
+        Edit {
+            line_number,
+            range,
+            line: line.as_bytes().to_vec(),
+        }
+    }
+
+    fn diff(input: &str, edits: &[Edit]) -> String {
+        let mut out = vec![];
+        write_diff("f", input.as_bytes(), edits, b'\n', &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn applies_edits() {
+        let input = b"a\nb\nc\nd\n";
+        let edits = [edit(2, 2..3, "B"), edit(3, 4..7, "X")];
+        assert_eq!(apply(input, &edits), b"a\nB\nX\n");
+    }
+
+    #[test]
+    fn diffs_single_line_edits() {
+        let input = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let edits = [edit(2, 2..3, "two"), edit(11, 21..23, "eleven")];
+        assert_eq!(
+            diff(input, &edits),
+            "--- f\n+++ f\n\
+             @@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n\
+             @@ -8,5 +8,5 @@\n 8\n 9\n 10\n-11\n+eleven\n 12\n"
+        );
+    }
+
+    #[test]
+    fn diffs_edits_spanning_lines() {
+        // `-U --replace X 'b\nc'` on four lines
+        let edits = [edit(2, 2..5, "X")];
+        assert_eq!(
+            diff("a\nb\nc\nd\n", &edits),
+            "--- f\n+++ f\n@@ -1,4 +1,3 @@\n a\n-b\n-c\n+X\n d\n"
+        );
+
+        // A replacement that adds lines moves the next hunk down
+        let input = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let edits = [edit(1, 0..1, "a\nb\nc"), edit(11, 21..26, "z")];
+        assert_eq!(
+            diff(input, &edits),
+            "--- f\n+++ f\n\
+             @@ -1,4 +1,6 @@\n-1\n+a\n+b\n+c\n 2\n 3\n 4\n\
+             @@ -8,5 +10,4 @@\n 8\n 9\n 10\n-11\n-12\n+z\n"
+        );
+    }
+
+    #[test]
+    fn marks_missing_final_newline() {
+        let edits = [edit(2, 2..3, "B")];
+        assert_eq!(
+            diff("a\nb", &edits),
+            "--- f\n+++ f\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+B\n\\ No newline at end of file\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn replaces_files_keeping_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("grep-rewrite-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("private.txt");
+        fs::write(&path, "old\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+        replace_file(&path, b"new\n", Some(".bak")).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"new\n");
+        assert_eq!(fs::read(dir.join("private.txt.bak")).unwrap(), b"old\n");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // No temporary file is left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn edits_files_in_their_encoding() {
+        use codecrafters_grep::replace::Template;
+        use codecrafters_grep::Regex;
+
+        use crate::searcher::SearchOptions;
+
+        let utf16 = |text: &str| -> Vec<u8> {
+            let mut bytes = vec![0xFF, 0xFE];
+            bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+            bytes
+        };
+
+        let dir = std::env::temp_dir().join(format!("grep-encoding-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("utf16.txt");
+        fs::write(&path, utf16("café\nthé\n")).unwrap();
+
+        let regex = Regex::new("é").unwrap();
+        let options = SearchOptions {
+            replace: Some(Template::new("e", regex.program())),
+            ..SearchOptions::default()
+        };
+        let searcher = Searcher::new(regex.program(), options);
+        let options = EditOptions {
+            backup_suffix: None,
+            dry_run: false,
+        };
+        edit_file(&searcher, path.to_str().unwrap(), &options, &mut vec![]).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), utf16("cafe\nthe\n"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
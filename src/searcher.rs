@@ -0,0 +1,919 @@
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use codecrafters_grep::matcher::{self, Slots, Tracer};
+use codecrafters_grep::program::Program;
+use codecrafters_grep::replace::Template;
+
+use crate::encoding::{Decoder, Encoding};
+use crate::json;
+
+/// Size of the first read; the buffer grows when a single line doesn't fit
+const INITIAL_CAPACITY: usize = 64 * 1024;
+
+/// How to treat input that looks like binary data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryFiles {
+    /// Report "binary file matches" instead of printing lines
+    Binary,
+    /// Search and print binary input as if it were text (`-a`)
+    Text,
+    /// Treat binary input as if it didn't match, from where it turns out to
+    /// be binary on
+    WithoutMatch,
+}
+
+/// What the searcher prints for each input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Output {
+    /// The matching lines
+    Lines,
+    /// Only the names of files with a match (`-l`)
+    FilesWithMatches,
+    /// Only the names of files without a match (`-L`)
+    FilesWithoutMatch,
+    /// Nothing at all, only whether something matched (`-q`)
+    Quiet,
+    /// One JSON object per event, described in the README (`--json`)
+    Json,
+}
+
+/// Which matches count, judged by their span in the line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Span {
+    /// Any match
+    Any,
+    /// Only matches not next to a word character (`-w`)
+    Word,
+    /// Only matches covering the whole line (`-x`)
+    Line,
+}
+
+/// Options that control what the searcher reports
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub binary_files: BinaryFiles,
+    pub output: Output,
+    pub span: Span,
+    /// Stop reading an input after this many matching lines (`-m`)
+    pub max_count: Option<u64>,
+    /// Prefix every printed line with the name of its file
+    pub with_filename: bool,
+    /// Byte that ends input and output records: `\n`, or NUL with `-z`
+    pub terminator: u8,
+    /// Follow file names with a NUL instead of `:` (`-Z`)
+    pub null_after_name: bool,
+    /// Print lines with every match replaced by this (`--replace`)
+    pub replace: Option<Template>,
+    /// Search each input as a whole, so matches can span lines (`-U`)
+    pub multiline: bool,
+    /// Transcode input from this encoding, unless it starts with a byte
+    /// order mark (`--encoding`)
+    pub encoding: Option<Encoding>,
+    /// Set once the outcome of the whole search is known, which stops the
+    /// inputs still being searched at their next block
+    pub cancelled: Arc<AtomicBool>,
+}
+
+impl Default for SearchOptions {
+    /// Options that print the matching lines of a single input
+    fn default() -> Self {
+        SearchOptions {
+            binary_files: BinaryFiles::Binary,
+            output: Output::Lines,
+            span: Span::Any,
+            max_count: None,
+            with_filename: false,
+            terminator: b'\n',
+            null_after_name: false,
+            replace: None,
+            multiline: false,
+            encoding: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+/// Searches input in large blocks and prints the lines that match
+///
+/// Rather than splitting the input into lines up front, the matcher runs over
+/// a whole block of complete lines at once. Line boundaries are only located
+/// around the matches it finds. The program must be compiled with the same
+/// line terminator as the searcher, so that no match can span two lines.
+///
+/// With `-U`, the program has no line terminator and every input is searched
+/// in one block, so matches can span lines. Each match then reports all the
+/// lines it covers.
+pub struct Searcher<'p> {
+    program: &'p Program,
+    options: SearchOptions,
+    buffer: Vec<u8>,
+}
+
+/// Progress of the search through a single input
+#[derive(Debug, Default)]
+pub struct State {
+    /// Whether any line matched so far
+    pub matched: bool,
+    /// Number of matching lines so far
+    pub count: u64,
+    /// Whether a NUL byte was seen, so the input is treated as binary
+    pub is_binary: bool,
+    /// Whether a match was found after the input turned out to be binary
+    pub binary_matched: bool,
+    /// Number of bytes searched before the current block
+    pub offset: u64,
+    /// Number of lines before the current block, only counted for `--json`
+    pub lines: u64,
+}
+
+/// A line rewritten by `--replace`, for editing files in place
+#[derive(Debug)]
+pub struct Edit {
+    /// Number of the line in its input, counting from 1
+    pub line_number: u64,
+    /// Byte range of the original lines, without the last terminator
+    pub range: Range<usize>,
+    /// The line after replacing every match
+    pub line: Vec<u8>,
+}
+
+/// What the searcher should do after handling a block
+#[derive(Debug, PartialEq)]
+pub enum Flow {
+    Continue,
+    Stop,
+}
+
+impl<'p> Searcher<'p> {
+    pub fn new(program: &'p Program, options: SearchOptions) -> Self {
+        Searcher {
+            program,
+            options,
+            buffer: vec![0; INITIAL_CAPACITY],
+        }
+    }
+
+    /// Returns the byte that ends each record
+    pub fn terminator(&self) -> u8 {
+        self.options.terminator
+    }
+
+    /// Returns the encoding inputs are transcoded from, if one was given
+    pub fn encoding(&self) -> Option<Encoding> {
+        self.options.encoding
+    }
+
+    /// Checks if inputs must be searched as a whole (`-U`)
+    pub fn multiline(&self) -> bool {
+        self.options.multiline
+    }
+
+    /// Searches `reader` and writes matching lines to `out`
+    ///
+    /// The input is transcoded to UTF-8 first if it's in another encoding. A
+    /// match in binary input isn't printed; the caller reports it when the
+    /// returned state has `binary_matched` set.
+    pub fn search<R: Read, W: Write>(
+        &mut self,
+        reader: R,
+        name: &str,
+        out: &mut W,
+    ) -> io::Result<State> {
+        let mut state = State::default();
+        let reader = Decoder::new(reader, self.options.encoding);
+        self.search_reader(reader, name, out, &mut state)?;
+        Ok(state)
+    }
+
+    /// Prints the name of a searched input when only file names are listed
+    pub fn finish<W: Write>(&self, name: &str, state: &State, out: &mut W) -> io::Result<()> {
+        let listed = match self.options.output {
+            Output::Lines | Output::Quiet => return Ok(()),
+            // Files without a match have no events at all
+            Output::Json if state.matched => {
+                let stats = format!(
+                    "{{\"matched_lines\":{},\"bytes_searched\":{}}}",
+                    state.count, state.offset
+                );
+                return writeln!(
+                    out,
+                    "{{\"type\":\"end\",\"data\":{{\"path\":{},\"stats\":{}}}}}",
+                    json::data(name.as_bytes()),
+                    stats
+                );
+            }
+            Output::Json => return Ok(()),
+            Output::FilesWithMatches => state.matched,
+            Output::FilesWithoutMatch => !state.matched,
+        };
+
+        if listed {
+            out.write_all(name.as_bytes())?;
+            out.write_all(if self.options.null_after_name {
+                b"\0"
+            } else {
+                b"\n"
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Searches the rest of `reader`, continuing from an existing state
+    pub fn search_reader<R: Read, W: Write>(
+        &mut self,
+        mut reader: R,
+        name: &str,
+        out: &mut W,
+        state: &mut State,
+    ) -> io::Result<()> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        let mut len = 0;
+
+        loop {
+            // Search whatever complete lines a read returns, so lines coming
+            // through a pipe are printed as they arrive
+            let read = len;
+            let eof = loop {
+                match reader.read(&mut buffer[len..]) {
+                    Ok(0) => break true,
+                    Ok(n) => {
+                        len += n;
+                        break false;
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) => {
+                        self.buffer = buffer;
+                        return Err(err);
+                    }
+                }
+            };
+
+            // Only search complete lines, unless there's nothing more to read.
+            // What's left of the last block has no terminator, so only the new
+            // bytes are scanned.
+            let last = buffer[read..len]
+                .iter()
+                .rposition(|&b| b == self.terminator());
+            let end = match last {
+                _ if eof => len,
+                // A match may span the whole input, so it's read in one piece
+                Some(i) if !self.options.multiline => read + i + 1,
+                _ => {
+                    // Grow the buffer once a single line fills it
+                    if len == buffer.len() {
+                        buffer.resize(buffer.len() * 2, 0);
+                    }
+                    continue;
+                }
+            };
+
+            let flow = self
+                .search_block(&buffer[..end], name, out, state)
+                .and_then(|flow| out.flush().map(|_| flow));
+            if eof || !matches!(flow, Ok(Flow::Continue)) {
+                self.buffer = buffer;
+                return flow.map(|_| ());
+            }
+
+            // Keep the trailing partial line for the next block
+            buffer.copy_within(end..len, 0);
+            len -= end;
+        }
+    }
+
+    /// Searches a block made of complete lines
+    ///
+    /// Only the last block of an input may end without a line terminator.
+    pub fn search_block<W: Write>(
+        &self,
+        block: &[u8],
+        name: &str,
+        out: &mut W,
+        state: &mut State,
+    ) -> io::Result<Flow> {
+        if self.options.cancelled.load(Ordering::Relaxed) {
+            return Ok(Flow::Stop);
+        }
+        let flow = self.search_lines(block, name, out, state);
+
+        state.offset += block.len() as u64;
+        if self.options.output == Output::Json {
+            state.lines += count(block, self.terminator());
+        }
+        flow
+    }
+
+    /// Searches a block, before its size is added to the state
+    fn search_lines<W: Write>(
+        &self,
+        block: &[u8],
+        name: &str,
+        out: &mut W,
+        state: &mut State,
+    ) -> io::Result<Flow> {
+        if self.options.max_count == Some(0) {
+            return Ok(Flow::Stop);
+        }
+
+        // Input containing a NUL byte is treated as binary, like GNU grep does,
+        // unless NUL separates the records
+        let terminator = self.terminator();
+        let binary_files = self.options.binary_files;
+        if binary_files != BinaryFiles::Text
+            && terminator != 0
+            && !state.is_binary
+            && block.contains(&0)
+        {
+            // Lines before the first NUL may already be printed, so they
+            // still count, like in GNU grep
+            if binary_files == BinaryFiles::WithoutMatch {
+                return Ok(Flow::Stop);
+            }
+            state.is_binary = true;
+        }
+
+        // Strip the final line terminator, so it doesn't produce an extra empty line
+        if block.is_empty() {
+            return Ok(Flow::Continue);
+        }
+        let text = block.strip_suffix(&[terminator]).unwrap_or(block);
+
+        // When only listing files or checking for a match, the first match
+        // settles the whole input
+        if state.is_binary || !matches!(self.options.output, Output::Lines | Output::Json) {
+            if self.find(text, 0).is_some() {
+                state.matched = true;
+                state.binary_matched = self.options.output == Output::Lines;
+                return Ok(Flow::Stop);
+            }
+            return Ok(Flow::Continue);
+        }
+
+        // Line numbers are counted up to each match, only when they're printed
+        let mut counted = 0;
+        let mut line_number = state.lines;
+
+        let mut pos = 0;
+        while pos <= text.len() {
+            let Some(slots) = self.find(text, pos) else {
+                break;
+            };
+
+            let (line_start, line_end) = self.lines_around(text, &slots);
+
+            if self.options.output == Output::Json {
+                if !state.matched {
+                    writeln!(
+                        out,
+                        "{{\"type\":\"begin\",\"data\":{{\"path\":{}}}}}",
+                        json::data(name.as_bytes())
+                    )?;
+                }
+
+                line_number += count(&text[counted..line_start], terminator);
+                counted = line_start;
+                writeln!(
+                    out,
+                    "{{\"type\":\"match\",\"data\":{{\"path\":{},\"lines\":{},\"line_number\":{},\"absolute_offset\":{},\"submatches\":[{}]}}}}",
+                    json::data(name.as_bytes()),
+                    json::data(&text[line_start..line_end]),
+                    line_number + 1,
+                    state.offset + line_start as u64,
+                    self.submatches(text, line_start, line_end, slots)
+                )?;
+            } else if let Some(template) = &self.options.replace {
+                let line = self.replace_line(template, text, line_start, line_end, slots);
+                self.write_line(&line, name, out)?;
+            } else {
+                self.write_line(&text[line_start..line_end], name, out)?;
+            }
+            state.matched = true;
+
+            state.count += 1;
+            if self.options.max_count == Some(state.count) {
+                return Ok(Flow::Stop);
+            }
+
+            // Resume searching on the next line
+            pos = line_end + 1;
+        }
+
+        Ok(Flow::Continue)
+    }
+
+    /// Collects the lines of a whole input that `--replace` rewrites
+    ///
+    /// Matches are found the same way as when printing lines, so `-w`, `-x`
+    /// and `-m` apply. Input that looks binary isn't rewritten, unless it's
+    /// searched as text.
+    pub fn edits(&self, input: &[u8], state: &mut State) -> Vec<Edit> {
+        let mut edits = vec![];
+        let Some(template) = &self.options.replace else {
+            return edits;
+        };
+
+        let terminator = self.terminator();
+        if self.options.binary_files != BinaryFiles::Text && terminator != 0 && input.contains(&0) {
+            state.is_binary = true;
+            return edits;
+        }
+        let text = input.strip_suffix(&[terminator]).unwrap_or(input);
+
+        let mut counted = 0;
+        let mut line_number = 0;
+        let mut pos = 0;
+        while pos <= text.len() && self.options.max_count != Some(state.count) {
+            let Some(slots) = self.find(text, pos) else {
+                break;
+            };
+
+            let (line_start, line_end) = self.lines_around(text, &slots);
+
+            line_number += count(&text[counted..line_start], terminator);
+            counted = line_start;
+            edits.push(Edit {
+                line_number: line_number + 1,
+                range: line_start..line_end,
+                line: self.replace_line(template, text, line_start, line_end, slots),
+            });
+
+            state.matched = true;
+            state.count += 1;
+            pos = line_end + 1;
+        }
+
+        state.offset = input.len() as u64;
+        edits
+    }
+
+    /// Builds a line with every match replaced by an expansion of `template`,
+    /// starting with the match in `slots`
+    fn replace_line(
+        &self,
+        template: &Template,
+        text: &[u8],
+        line_start: usize,
+        line_end: usize,
+        slots: Slots,
+    ) -> Vec<u8> {
+        let mut line = vec![];
+        let mut last = line_start;
+
+        for slots in self.line_matches(text, line_end, slots) {
+            line.extend_from_slice(&text[last..slots[0].unwrap()]);
+            template.expand(text, &slots, &mut line);
+            last = slots[1].unwrap();
+        }
+
+        line.extend_from_slice(&text[last..line_end]);
+        line
+    }
+
+    /// Prints a matching line, after the file name when there are several files
+    fn write_line<W: Write>(&self, line: &[u8], name: &str, out: &mut W) -> io::Result<()> {
+        if self.options.with_filename {
+            out.write_all(name.as_bytes())?;
+            out.write_all(if self.options.null_after_name {
+                b"\0"
+            } else {
+                b":"
+            })?;
+        }
+        out.write_all(line)?;
+        out.write_all(&[self.terminator()])
+    }
+
+    /// Lists every match in a line as JSON, starting with the one in `slots`
+    ///
+    /// Each match comes with its capture groups. Offsets are relative to the
+    /// start of the line.
+    fn submatches(&self, text: &[u8], line_start: usize, line_end: usize, slots: Slots) -> String {
+        let span = |start: usize, end: usize| {
+            format!(
+                "\"match\":{},\"start\":{},\"end\":{}",
+                json::data(&text[start..end]),
+                start - line_start,
+                end - line_start
+            )
+        };
+
+        let submatches: Vec<String> = self
+            .line_matches(text, line_end, slots)
+            .iter()
+            .map(|slots| {
+                let groups: Vec<String> = (1..=self.program.groups)
+                    .map(|group| match (slots[2 * group], slots[2 * group + 1]) {
+                        (Some(start), Some(end)) => format!("{{{}}}", span(start, end)),
+                        _ => "null".to_string(),
+                    })
+                    .collect();
+                format!(
+                    "{{{},\"groups\":[{}]}}",
+                    span(slots[0].unwrap(), slots[1].unwrap()),
+                    groups.join(",")
+                )
+            })
+            .collect();
+
+        submatches.join(",")
+    }
+
+    /// Locates the start and end of the lines a match covers
+    ///
+    /// Without `-U` that's the single line the match is in. The end excludes
+    /// the terminator, even when the match includes it.
+    fn lines_around(&self, text: &[u8], slots: &Slots) -> (usize, usize) {
+        let terminator = self.terminator();
+        let (start, end) = (slots[0].unwrap(), slots[1].unwrap());
+        let last = end.saturating_sub(1).max(start);
+
+        let line_start = text[..start]
+            .iter()
+            .rposition(|&b| b == terminator)
+            .map_or(0, |i| i + 1);
+        let line_end = text[last..]
+            .iter()
+            .position(|&b| b == terminator)
+            .map_or(text.len(), |i| last + i);
+        (line_start, line_end)
+    }
+
+    /// Collects every match in a line, starting with the one in `slots`
+    ///
+    /// Like `Regex::captures_iter`, an empty match right where the previous
+    /// match ended is skipped.
+    fn line_matches(&self, text: &[u8], line_end: usize, slots: Slots) -> Vec<Slots> {
+        let mut matches = vec![];
+        let mut next = Some(slots);
+        let mut last_end = None;
+
+        while let Some(slots) = next.take() {
+            let (start, end) = (slots[0].unwrap(), slots[1].unwrap());
+            if start > line_end {
+                break;
+            }
+
+            let pos = if start == end && last_end == Some(end) {
+                // Step over the next character and try again
+                end + 1
+            } else {
+                matches.push(slots);
+                last_end = Some(end);
+                end
+            };
+            if pos <= line_end {
+                next = self.find(text, pos);
+            }
+        }
+
+        matches
+    }
+
+    /// Finds the next match at or after `pos` whose span is acceptable
+    fn find(&self, text: &[u8], pos: usize) -> Option<Slots> {
+        self.with_span_check(text, |accept| match accept {
+            Some(accept) => matcher::find_accepted(self.program, text, pos, accept),
+            None => matcher::find_at(self.program, text, pos),
+        })
+    }
+
+    /// Finds the next match like `find` does, reporting every step of the
+    /// matcher to `tracer` (`--trace`)
+    pub fn trace(&self, text: &[u8], pos: usize, tracer: &mut dyn Tracer) -> Option<Slots> {
+        self.with_span_check(text, |accept| {
+            matcher::trace(self.program, text, pos, accept, tracer)
+        })
+    }
+
+    /// Runs `find` with the check that a match spans a whole word (`-w`) or
+    /// line (`-x`), or `None` when any span will do
+    fn with_span_check<T>(
+        &self,
+        text: &[u8],
+        find: impl FnOnce(Option<&dyn Fn(usize, usize) -> bool>) -> T,
+    ) -> T {
+        let terminator = self.terminator();
+        match self.options.span {
+            Span::Any => find(None),
+            Span::Word => find(Some(&|start, end| {
+                matcher::is_whole_word(self.program, text, start, end)
+            })),
+            Span::Line => find(Some(&|start, end| {
+                (start == 0 || text[start - 1] == terminator)
+                    && (end == text.len() || text[end] == terminator)
+            })),
+        }
+    }
+}
+
+/// Counts the records ended by `terminator` in `bytes`
+fn count(bytes: &[u8], terminator: u8) -> u64 {
+    bytes.iter().filter(|&&b| b == terminator).count() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codecrafters_grep::{Config, Regex};
+
+    fn regex(pattern: &str) -> Regex {
+        let config = Config {
+            line_terminator: Some(b'\n'),
+            ..Config::default()
+        };
+        Regex::with_config(pattern, config).unwrap()
+    }
+
+    fn search(pattern: &str, input: &str, options: SearchOptions) -> String {
+        let regex = regex(pattern);
+        let mut searcher = Searcher::new(regex.program(), options);
+        let mut out = vec![];
+        searcher.search(input.as_bytes(), "-", &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn search_state(pattern: &str, input: &[u8], options: SearchOptions) -> (String, State) {
+        let regex = regex(pattern);
+        let mut searcher = Searcher::new(regex.program(), options);
+        let mut out = vec![];
+        let state = searcher.search(input, "-", &mut out).unwrap();
+        (String::from_utf8(out).unwrap(), state)
+    }
+
+    fn replace(pattern: &str, template: &str, input: &str) -> String {
+        let options = SearchOptions {
+            replace: Some(Template::new(template, regex(pattern).program())),
+            ..SearchOptions::default()
+        };
+        search(pattern, input, options)
+    }
+
+    #[test]
+    fn prints_matching_lines() {
+        assert_eq!(
+            search("b+", "abc\ndef\nbb\n", SearchOptions::default()),
+            "abc\nbb\n"
+        );
+    }
+
+    /// Returns at most one of its chunks per read, noting what was flushed
+    /// to `out` before each read
+    struct Chunks<'a> {
+        chunks: std::slice::Iter<'a, &'a str>,
+        chunk: &'a [u8],
+        out: &'a std::cell::RefCell<Vec<u8>>,
+        seen: Vec<String>,
+    }
+
+    impl Read for Chunks<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.seen
+                .push(String::from_utf8(self.out.borrow().clone()).unwrap());
+            if self.chunk.is_empty() {
+                self.chunk = self.chunks.next().map_or(b"", |c| c.as_bytes());
+            }
+            let len = self.chunk.len().min(buf.len());
+            buf[..len].copy_from_slice(&self.chunk[..len]);
+            self.chunk = &self.chunk[len..];
+            Ok(len)
+        }
+    }
+
+    /// Appends to a shared buffer only when flushed
+    struct Flushed<'a> {
+        pending: Vec<u8>,
+        out: &'a std::cell::RefCell<Vec<u8>>,
+    }
+
+    impl Write for Flushed<'_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.pending.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.out.borrow_mut().append(&mut self.pending);
+            Ok(())
+        }
+    }
+
+    /// Searches input arriving in `chunks`, returning what was flushed
+    /// before each read
+    fn search_chunks(pattern: &str, chunks: &[&str]) -> Vec<String> {
+        let regex = regex(pattern);
+        let mut searcher = Searcher::new(regex.program(), SearchOptions::default());
+        let flushed = std::cell::RefCell::new(vec![]);
+        let mut reader = Chunks {
+            chunks: chunks.iter(),
+            chunk: b"",
+            out: &flushed,
+            seen: vec![],
+        };
+        let mut out = Flushed {
+            pending: vec![],
+            out: &flushed,
+        };
+        let mut state = State::default();
+        searcher
+            .search_reader(&mut reader, "-", &mut out, &mut state)
+            .unwrap();
+        reader.seen.push(String::from_utf8(flushed.take()).unwrap());
+        reader.seen
+    }
+
+    #[test]
+    fn prints_lines_as_they_arrive() {
+        let seen = search_chunks("hello|world", &["hello\n", "wor", "ld\nx", "\n"]);
+        assert_eq!(
+            seen,
+            [
+                "",
+                "hello\n",
+                "hello\n",
+                "hello\nworld\n",
+                "hello\nworld\n",
+                "hello\nworld\n"
+            ]
+        );
+    }
+
+    #[test]
+    fn joins_lines_split_across_reads() {
+        let seen = search_chunks("ab", &["xa", "b", "y\nz\na", "b"]);
+        assert_eq!(seen.last().unwrap(), "xaby\nab\n");
+
+        // A line longer than the buffer grows it
+        let long = format!("{}ab", "x".repeat(INITIAL_CAPACITY * 2 + 100));
+        let line = format!("{}\n", long);
+        let (first, rest) = line.split_at(INITIAL_CAPACITY - 1);
+        let seen = search_chunks("ab$", &["x\n", first, rest]);
+        assert_eq!(seen.last().unwrap(), &line);
+    }
+
+    #[test]
+    fn checks_spans() {
+        let word = SearchOptions {
+            span: Span::Word,
+            ..SearchOptions::default()
+        };
+        assert_eq!(search("foo", "foobar\nfoo bar\n", word), "foo bar\n");
+
+        let line = SearchOptions {
+            span: Span::Line,
+            ..SearchOptions::default()
+        };
+        assert_eq!(search("a+", "aab\naa\n", line), "aa\n");
+    }
+
+    #[test]
+    fn replaces_empty_matches_once() {
+        assert_eq!(replace("a*", "X", "aaa\n"), "X\n");
+        assert_eq!(replace("a*", "X", "baaac\n"), "XbXcX\n");
+        assert_eq!(replace("", "-", "ab\n"), "-a-b-\n");
+    }
+
+    #[test]
+    fn stops_at_binary_data_without_match() {
+        let regex = regex("a");
+        let options = SearchOptions {
+            binary_files: BinaryFiles::WithoutMatch,
+            ..SearchOptions::default()
+        };
+        let searcher = Searcher::new(regex.program(), options);
+
+        let mut out = vec![];
+        let mut state = State::default();
+        let flow = searcher.search_block(b"a\n", "-", &mut out, &mut state);
+        assert_eq!(flow.unwrap(), Flow::Continue);
+        let flow = searcher.search_block(b"a\0\na\n", "-", &mut out, &mut state);
+        assert_eq!(flow.unwrap(), Flow::Stop);
+        assert_eq!(out, b"a\n");
+        assert!(state.matched);
+        assert_eq!(state.count, 1);
+    }
+
+    #[test]
+    fn stops_once_cancelled() {
+        let regex = regex("a");
+        let options = SearchOptions::default();
+        options.cancelled.store(true, Ordering::Relaxed);
+        let searcher = Searcher::new(regex.program(), options);
+
+        let mut out = vec![];
+        let mut state = State::default();
+        let flow = searcher.search_block(b"a\n", "-", &mut out, &mut state);
+        assert_eq!(flow.unwrap(), Flow::Stop);
+        assert!(!state.matched);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn treats_input_with_nul_as_binary() {
+        let input = b"a\0\nab\n";
+        let (out, state) = search_state("b", input, SearchOptions::default());
+        assert_eq!(out, "");
+        assert!(state.matched && state.binary_matched);
+
+        let text = SearchOptions {
+            binary_files: BinaryFiles::Text,
+            ..SearchOptions::default()
+        };
+        let (out, state) = search_state("b", input, text);
+        assert_eq!(out, "ab\n");
+        assert!(!state.binary_matched);
+
+        let without_match = SearchOptions {
+            binary_files: BinaryFiles::WithoutMatch,
+            ..SearchOptions::default()
+        };
+        let (out, state) = search_state("b", input, without_match);
+        assert_eq!(out, "");
+        assert!(!state.matched);
+
+        let (_, state) = search_state("c", input, SearchOptions::default());
+        assert!(state.is_binary && !state.matched);
+    }
+
+    #[test]
+    fn separates_records_with_nul() {
+        let config = Config {
+            line_terminator: Some(0),
+            ..Config::default()
+        };
+        let regex = Regex::with_config("^b", config).unwrap();
+        let options = SearchOptions {
+            terminator: 0,
+            with_filename: true,
+            null_after_name: true,
+            ..SearchOptions::default()
+        };
+        let mut searcher = Searcher::new(regex.program(), options);
+
+        // Records can hold newlines, and NUL doesn't make them binary
+        let mut out = vec![];
+        let state = searcher
+            .search(&b"a\nb\0b\nc\0"[..], "f", &mut out)
+            .unwrap();
+        assert_eq!(out, b"f\0b\nc\0");
+        assert!(!state.is_binary);
+    }
+
+    #[test]
+    fn ends_listed_names_with_nul() {
+        let regex = regex("a");
+        let options = SearchOptions {
+            output: Output::FilesWithMatches,
+            null_after_name: true,
+            ..SearchOptions::default()
+        };
+        let mut searcher = Searcher::new(regex.program(), options);
+
+        let mut out = vec![];
+        let state = searcher.search(&b"a\n"[..], "f", &mut out).unwrap();
+        searcher.finish("f", &state, &mut out).unwrap();
+        assert_eq!(out, b"f\0");
+    }
+
+    /// Searches every input and lists their names the way `output` does
+    fn list(pattern: &str, inputs: &[(&str, &str)], output: Output) -> String {
+        let regex = regex(pattern);
+        let options = SearchOptions {
+            output,
+            ..SearchOptions::default()
+        };
+        let mut searcher = Searcher::new(regex.program(), options);
+
+        let mut out = vec![];
+        for (name, input) in inputs {
+            let state = searcher.search(input.as_bytes(), name, &mut out).unwrap();
+            searcher.finish(name, &state, &mut out).unwrap();
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn lists_files_with_and_without_matches() {
+        let inputs = [("a", "x\nb\nb\n"), ("b", "x\n"), ("c", "b\n")];
+        assert_eq!(list("b", &inputs, Output::FilesWithMatches), "a\nc\n");
+        assert_eq!(list("b", &inputs, Output::FilesWithoutMatch), "b\n");
+        assert_eq!(list("b", &inputs, Output::Quiet), "");
+    }
+
+    #[test]
+    fn stops_after_max_count_lines() {
+        let options = |max_count| SearchOptions {
+            max_count,
+            ..SearchOptions::default()
+        };
+        let (out, state) = search_state("a", b"a1\nb\na2\na3\n", options(Some(2)));
+        assert_eq!(out, "a1\na2\n");
+        assert_eq!(state.count, 2);
+
+        let (out, state) = search_state("a", b"a1\n", options(Some(0)));
+        assert_eq!(out, "");
+        assert!(!state.matched);
+    }
+}
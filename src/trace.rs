@@ -0,0 +1,257 @@
+use std::io::{self, Write};
+
+use codecrafters_grep::explain;
+use codecrafters_grep::matcher::{Event, Tracer};
+use codecrafters_grep::program::{Inst, Program};
+
+use crate::searcher::Searcher;
+
+/// Steps traced when `--trace` isn't given a limit
+pub const DEFAULT_LIMIT: u64 = 1000;
+
+/// Prints the events of the matcher as they happen
+struct Log<'a, W> {
+    program: &'a Program,
+    out: &'a mut W,
+    /// Steps taken so far, over all lines
+    steps: u64,
+    limit: u64,
+    /// The last split on the current path, and whether it took its first
+    /// target
+    path: Option<(usize, bool)>,
+    /// The first error writing the log, which stops the trace
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Tracer for Log<'_, W> {
+    fn event(&mut self, event: Event) -> bool {
+        if let Event::Step { .. } = event {
+            self.steps += 1;
+            if self.steps > self.limit {
+                return false;
+            }
+        }
+
+        let result = match event {
+            Event::Attempt { pos } => {
+                self.path = None;
+                writeln!(self.out, "  attempt at {}", pos)
+            }
+            Event::Step { pc, pos } => {
+                let origin = match &self.program.origins[pc] {
+                    Some(origin) => {
+                        self.path = Some((pc, true));
+                        format!(" ({})", explain::split_origin(origin))
+                    }
+                    None => String::new(),
+                };
+                writeln!(
+                    self.out,
+                    "    {:>4} @{:<4} {}{}",
+                    pc,
+                    pos,
+                    explain::instruction(&self.program.insts[pc], self.program),
+                    origin
+                )
+            }
+            // Only the span check of -w and -x fails a match
+            Event::Fail { pc, pos } if self.program.insts[pc] == Inst::Match => writeln!(
+                self.out,
+                "    fail at {} @{}, not a whole word or line",
+                pc, pos
+            ),
+            Event::Fail { pc, pos } => {
+                writeln!(self.out, "    fail at {} @{}{}", pc, pos, self.path())
+            }
+            Event::Backtrack { split, pc, pos } => {
+                self.path = Some((split, false));
+                writeln!(self.out, "    backtrack to {} @{}{}", pc, pos, self.path())
+            }
+            Event::Match { start, end } => writeln!(self.out, "  match {}..{}", start, end),
+        };
+
+        match result {
+            Ok(()) => true,
+            Err(err) => {
+                self.error = Some(err);
+                false
+            }
+        }
+    }
+}
+
+impl<W> Log<'_, W> {
+    /// Describes the path the last split took, like `: split 3, branch 2 of 2`
+    fn path(&self) -> String {
+        match self.path {
+            Some((split, first)) => match &self.program.origins[split] {
+                Some(origin) => {
+                    format!(": split {}, {}", split, explain::split_path(origin, first))
+                }
+                None => String::new(),
+            },
+            None => String::new(),
+        }
+    }
+}
+
+/// Looks for a match on every line of `text` the way `searcher` does,
+/// printing each step the matcher takes
+///
+/// With `-U`, matches can span lines, so the input is traced as a whole. All
+/// lines share a budget of `limit` steps, so a pattern that backtracks a lot
+/// can't flood the output. Returns whether any line matched, and whether the
+/// budget ran out.
+pub fn trace_input<W: Write>(
+    searcher: &Searcher,
+    program: &Program,
+    name: &str,
+    text: &[u8],
+    limit: u64,
+    steps: &mut u64,
+    out: &mut W,
+) -> io::Result<(bool, bool)> {
+    let terminator = searcher.terminator();
+    let text = text.strip_suffix(&[terminator]).unwrap_or(text);
+    let mut matched = false;
+
+    let lines: Vec<&[u8]> = match searcher.multiline() {
+        true => vec![text],
+        false => text.split(|&b| b == terminator).collect(),
+    };
+    for (number, line) in lines.into_iter().enumerate() {
+        if searcher.multiline() {
+            writeln!(out, "{}: whole input, {} bytes", name, line.len())?;
+        } else {
+            writeln!(
+                out,
+                "{}:{}: {:?}",
+                name,
+                number + 1,
+                String::from_utf8_lossy(line)
+            )?;
+        }
+
+        let mut log = Log {
+            program,
+            out,
+            steps: *steps,
+            limit,
+            path: None,
+            error: None,
+        };
+        let found = searcher.trace(line, 0, &mut log).is_some();
+        *steps = log.steps;
+        if let Some(err) = log.error {
+            return Err(err);
+        }
+
+        if *steps > limit {
+            writeln!(out, "trace stopped after {} steps", limit)?;
+            return Ok((matched, true));
+        }
+        if !found {
+            writeln!(out, "  no match")?;
+        }
+        matched |= found;
+    }
+
+    Ok((matched, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use codecrafters_grep::{Config, Regex};
+
+    use super::*;
+    use crate::searcher::{SearchOptions, Span};
+
+    /// Traces `text`, returning the log, whether it matched and whether the
+    /// limit was reached
+    fn trace_limited(
+        pattern: &str,
+        text: &str,
+        span: Span,
+        multiline: bool,
+        limit: u64,
+    ) -> (String, bool, bool) {
+        let config = Config {
+            line_terminator: (!multiline).then_some(b'\n'),
+            multi_line: multiline,
+            ..Config::default()
+        };
+        let regex = Regex::with_config(pattern, config).unwrap();
+        let options = SearchOptions {
+            span,
+            multiline,
+            ..SearchOptions::default()
+        };
+        let searcher = Searcher::new(regex.program(), options);
+
+        let mut out = vec![];
+        let mut steps = 0;
+        let (matched, stopped) = trace_input(
+            &searcher,
+            regex.program(),
+            "-",
+            text.as_bytes(),
+            limit,
+            &mut steps,
+            &mut out,
+        )
+        .unwrap();
+        (String::from_utf8(out).unwrap(), matched, stopped)
+    }
+
+    fn trace(pattern: &str, text: &str, span: Span, multiline: bool) -> (String, bool) {
+        let (log, matched, stopped) = trace_limited(pattern, text, span, multiline, DEFAULT_LIMIT);
+        assert!(!stopped);
+        (log, matched)
+    }
+
+    #[test]
+    fn agrees_with_span_checks() {
+        let (log, matched) = trace("foo", "foobar\n", Span::Word, false);
+        assert!(!matched);
+        assert!(log.contains("not a whole word or line"));
+        assert!(log.ends_with("  no match\n"));
+
+        let (_, matched) = trace("foo", "foo bar\n", Span::Word, false);
+        assert!(matched);
+        let (_, matched) = trace("a+", "aab\n", Span::Line, false);
+        assert!(!matched);
+    }
+
+    #[test]
+    fn traces_whole_inputs_when_multiline() {
+        let (log, matched) = trace("a\nb", "a\nb\n", Span::Any, true);
+        assert!(matched);
+        assert!(log.starts_with("-: whole input, 3 bytes\n"));
+    }
+
+    #[test]
+    fn stops_at_the_limit() {
+        let text = "aaaaaaaaaaaaaaaaaaaa\n";
+        let (log, _, stopped) = trace_limited("(a|aa)*c", text, Span::Any, false, 10);
+        assert!(stopped);
+        assert!(log.ends_with("trace stopped after 10 steps\n"));
+    }
+
+    #[test]
+    fn tells_which_branch_is_tried() {
+        let (log, matched) = trace("(a|ab)c", "abc\n", Span::Any, false);
+        assert!(matched);
+        assert!(log.contains(" split 3, 5 (branch 1 of 2)\n"));
+        // `a` matches, then `c` fails against `b`
+        assert!(log.contains("    fail at 8 @1: split 2, branch 1 of 2\n"));
+        assert!(log.contains("    backtrack to 5 @0: split 2, branch 2 of 2\n"));
+    }
+
+    #[test]
+    fn tells_which_way_a_repeat_goes() {
+        let (log, _) = trace("a*b", "aac\n", Span::Any, false);
+        assert!(log.contains(" (greedy repeat of 2..3)\n"));
+        assert!(log.contains("fail at 2 @2: split 1, another run of greedy repeat of 2..3\n"));
+        assert!(log.contains("backtrack to 4 @2: split 1, past greedy repeat of 2..3\n"));
+    }
+}
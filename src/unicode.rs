@@ -0,0 +1,116 @@
+//! Unicode property tables for `\p{...}`
+//!
+//! The tables are generated at build time from the UCD files in `ucd/`.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+mod tables {
+    include!(concat!(env!("OUT_DIR"), "/unicode_tables.rs"));
+}
+
+/// A Unicode property, like a general category, a script or a binary property
+#[derive(Clone, Copy)]
+pub struct Property {
+    name: &'static str,
+    /// Sorted, non-overlapping code point ranges
+    ranges: &'static [(char, char)],
+}
+
+impl Property {
+    /// Looks up a property by the name used in `\p{...}`
+    ///
+    /// Accepts `L`, `Letter`, `Greek`, `White_Space`, and the qualified forms
+    /// `gc=Lu` and `sc=Greek`. Like in the UCD, case, spaces, `-` and `_` in
+    /// names are ignored.
+    pub fn lookup(query: &str) -> Option<Property> {
+        let (kind, value) = match query.split_once(['=', ':']) {
+            Some((kind, value)) => (Some(loose(kind)), value),
+            None => (None, query),
+        };
+
+        let value = loose(value);
+
+        // Resolve the aliases of the value to a table of the given kind
+        let find = |tables: &'static [(&'static str, &'static [(char, char)])]| {
+            tables::ALIASES
+                .iter()
+                .filter(|(alias, _)| loose(alias) == value)
+                .find_map(|&(_, canonical)| tables.iter().find(|(name, _)| *name == canonical))
+                .map(|&(name, ranges)| Property { name, ranges })
+        };
+
+        match kind.as_deref() {
+            Some("gc" | "generalcategory") => find(tables::GENERAL_CATEGORIES),
+            Some("sc" | "script") => find(tables::SCRIPTS),
+            Some(_) => None,
+            None => find(tables::GENERAL_CATEGORIES)
+                .or_else(|| find(tables::SCRIPTS))
+                .or_else(|| {
+                    tables::BINARY_PROPERTIES
+                        .iter()
+                        .find(|(name, _)| loose(name) == value)
+                        .map(|&(name, ranges)| Property { name, ranges })
+                }),
+        }
+    }
+
+    /// Returns the canonical name of the property
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Checks if a character has this property
+    pub fn contains(&self, c: char) -> bool {
+        self.ranges
+            .binary_search_by(|&(start, end)| {
+                if end < c {
+                    std::cmp::Ordering::Less
+                } else if start > c {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+}
+
+impl fmt::Debug for Property {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Property({})", self.name)
+    }
+}
+
+impl PartialEq for Property {
+    fn eq(&self, other: &Property) -> bool {
+        self.name == other.name
+    }
+}
+
+/// Checks if a character is a decimal digit (`\p{Nd}`)
+pub fn is_digit(c: char) -> bool {
+    static DIGIT: OnceLock<Property> = OnceLock::new();
+    DIGIT.get_or_init(|| known("Nd")).contains(c)
+}
+
+/// Checks if a character is a word character, as UTS #18 defines `\w`
+pub fn is_word(c: char) -> bool {
+    static WORD: OnceLock<[Property; 5]> = OnceLock::new();
+    WORD.get_or_init(|| ["Alphabetic", "M", "Nd", "Pc", "Join_Control"].map(known))
+        .iter()
+        .any(|property| property.contains(c))
+}
+
+/// Looks up a property that the bundled tables always have
+fn known(name: &str) -> Property {
+    Property::lookup(name).unwrap_or_else(|| panic!("missing Unicode table for {}", name))
+}
+
+/// Normalizes a property name for loose matching, as UAX #44 recommends
+fn loose(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '_' | '-'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
@@ -0,0 +1,173 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::glob::Glob;
+use crate::ignore::Ignore;
+
+/// One input to search, or an error found while looking for inputs
+pub enum Entry {
+    /// A file to search, or "-" for stdin
+    File(String),
+    /// A directory that couldn't be read
+    Error(String, io::Error),
+}
+
+/// Options that decide which inputs are searched
+pub struct WalkOptions {
+    /// Search directories recursively (`-r`)
+    pub recursive: bool,
+    /// Visit the entries of a directory in name order
+    pub sorted: bool,
+    /// Only search files matching one of these, if there are any (`--include`)
+    pub include: Vec<Glob>,
+    /// Skip files matching one of these (`--exclude`)
+    pub exclude: Vec<Glob>,
+    /// Skip directories matching one of these (`--exclude-dir`)
+    pub exclude_dir: Vec<Glob>,
+    /// Skip what `.gitignore`, `.ignore` and `.git/info/exclude` files ignore
+    pub ignore_files: bool,
+}
+
+impl WalkOptions {
+    /// Checks if a file passes the `--include` and `--exclude` globs
+    fn selects_file(&self, path: &str) -> bool {
+        (self.include.is_empty() || matches_any(&self.include, path))
+            && !matches_any(&self.exclude, path)
+    }
+}
+
+/// Checks if any glob matches a path
+///
+/// Globs are tried on the last component of the path and on the whole path,
+/// so `*.rs` selects Rust files in every directory and `src/*.rs` those in
+/// `src`.
+pub fn matches_any(globs: &[Glob], path: &str) -> bool {
+    let name = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(path);
+    globs
+        .iter()
+        .any(|glob| glob.is_match(name) || glob.is_match(path))
+}
+
+/// Checks if the innermost ignore file with an opinion ignores an entry
+fn is_ignored(ignores: &[Ignore], path: &Path, is_dir: bool) -> bool {
+    ignores
+        .iter()
+        .rev()
+        .find_map(|ignore| ignore.matched(path, is_dir))
+        .unwrap_or(false)
+}
+
+/// Expands the command-line operands into the list of inputs to search
+///
+/// Without `recursive`, operands are returned as they are, apart from files
+/// left out by the globs. Otherwise every directory is walked, skipping
+/// symbolic links found inside it. Entries of a directory are visited in name
+/// order when `sorted` is set, and in the order the file system returns them
+/// otherwise.
+///
+/// Without operands, stdin is searched, or the current directory when
+/// `recursive` is set.
+pub fn collect(operands: &[String], options: &WalkOptions) -> Vec<Entry> {
+    let mut entries = vec![];
+
+    if operands.is_empty() {
+        if options.recursive {
+            walk_root("", options, &mut entries);
+        } else {
+            entries.push(Entry::File("-".to_string()));
+        }
+    }
+
+    for operand in operands {
+        if operand == "-" {
+            entries.push(Entry::File(operand.clone()));
+        } else if options.recursive && Path::new(operand).is_dir() {
+            if !matches_any(&options.exclude_dir, operand) {
+                walk_root(operand, options, &mut entries);
+            }
+        } else if options.selects_file(operand) {
+            entries.push(Entry::File(operand.clone()));
+        }
+    }
+
+    entries
+}
+
+/// Walks a directory given on the command line, with the ignore files of
+/// the directories above it
+fn walk_root(dir: &str, options: &WalkOptions, entries: &mut Vec<Entry>) {
+    let mut ignores = match options.ignore_files {
+        true => Ignore::read_parents(Path::new(if dir.is_empty() { "." } else { dir })),
+        false => vec![],
+    };
+
+    walk(dir, options, &mut ignores, entries);
+}
+
+/// Recursively adds the files below `dir` to `entries`
+///
+/// An empty `dir` stands for the current directory, whose files are named
+/// without a "./" prefix.
+fn walk(dir: &str, options: &WalkOptions, ignores: &mut Vec<Ignore>, entries: &mut Vec<Entry>) {
+    let dir_path = if dir.is_empty() { "." } else { dir };
+    let children = match fs::read_dir(dir_path) {
+        Ok(children) => children,
+        Err(err) => {
+            entries.push(Entry::Error(dir_path.to_string(), err));
+            return;
+        }
+    };
+
+    // Ignore rules are matched against absolute paths
+    let absolute: Option<PathBuf> = match options.ignore_files {
+        true => fs::canonicalize(dir_path).ok(),
+        false => None,
+    };
+    let outer_ignores = ignores.len();
+    if let Some(absolute) = &absolute {
+        ignores.extend(Ignore::read_dir(absolute));
+    }
+
+    let mut children: Vec<_> = children.filter_map(|child| child.ok()).collect();
+    if options.sorted {
+        children.sort_by_key(|child| child.file_name());
+    }
+
+    for child in children {
+        let name = child.file_name();
+        let path = match dir {
+            "" => name.to_string_lossy().into_owned(),
+            _ => format!("{}/{}", dir.trim_end_matches('/'), name.to_string_lossy()),
+        };
+
+        let kind = match child.file_type() {
+            Ok(kind) => kind,
+            Err(err) => {
+                entries.push(Entry::Error(path, err));
+                continue;
+            }
+        };
+        let ignored = |is_dir| {
+            absolute
+                .as_ref()
+                .is_some_and(|absolute| is_ignored(ignores, &absolute.join(&name), is_dir))
+        };
+
+        if kind.is_dir() {
+            // The repository itself is never searched when ignore files are used
+            let is_git = options.ignore_files && name == ".git";
+            if !is_git && !matches_any(&options.exclude_dir, &path) && !ignored(true) {
+                walk(&path, options, ignores, entries);
+            }
+        } else if !kind.is_symlink() && options.selects_file(&path) && !ignored(false) {
+            entries.push(Entry::File(path));
+        }
+    }
+
+    ignores.truncate(outer_ignores);
+}